@@ -1,4 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
+use fsprompt::core::encoding::EncodingConfig;
 use fsprompt::core::types::CanonicalPath;
 use fsprompt::utils::parallel_fs::{read_files_parallel, scan_directory_parallel};
 use rayon::prelude::*;
@@ -119,7 +120,8 @@ fn bench_file_reading(c: &mut Criterion) {
                 .iter()
                 .filter_map(|p| CanonicalPath::new(p).ok())
                 .collect();
-            let results = read_files_parallel(&canonical_paths, 256 * 1024); // 256KB threshold
+            let results =
+                read_files_parallel(&canonical_paths, 256 * 1024, EncodingConfig::default()); // 256KB threshold
             let total_size: usize = results
                 .iter()
                 .filter_map(|(_, result)| result.as_ref().ok())
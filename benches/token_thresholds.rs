@@ -1,4 +1,5 @@
 use criterion::{criterion_group, criterion_main, Criterion};
+use fsprompt::core::encoding::EncodingConfig;
 use fsprompt::core::types::CanonicalPath;
 use fsprompt::utils::parallel_fs::{read_files_parallel, scan_directory_parallel};
 use std::fs;
@@ -189,7 +190,7 @@ fn bench_20k_tokens(c: &mut Criterion) {
                 .collect();
 
             // 3. Read files
-            let contents = read_files_parallel(&file_paths, 256 * 1024);
+            let contents = read_files_parallel(&file_paths, 256 * 1024, EncodingConfig::default());
 
             // 4. Generate output
             let mut output = String::with_capacity(100 * 1024);
@@ -227,7 +228,7 @@ fn bench_100k_tokens(c: &mut Criterion) {
                 .map(|e| e.path.clone())
                 .collect();
 
-            let contents = read_files_parallel(&file_paths, 256 * 1024);
+            let contents = read_files_parallel(&file_paths, 256 * 1024, EncodingConfig::default());
 
             let mut output = String::with_capacity(500 * 1024);
             for (path, content) in contents {
@@ -264,7 +265,7 @@ fn bench_200k_tokens(c: &mut Criterion) {
                 .map(|e| e.path.clone())
                 .collect();
 
-            let contents = read_files_parallel(&file_paths, 256 * 1024);
+            let contents = read_files_parallel(&file_paths, 256 * 1024, EncodingConfig::default());
 
             let mut output = String::with_capacity(1024 * 1024);
             for (path, content) in contents {
@@ -314,7 +315,7 @@ fn bench_operations_by_scale(c: &mut Criterion) {
 
         // Benchmark file reading
         group.bench_function("read_files", |b| {
-            b.iter(|| read_files_parallel(&file_paths, 256 * 1024));
+            b.iter(|| read_files_parallel(&file_paths, 256 * 1024, EncodingConfig::default()));
         });
 
         group.finish();
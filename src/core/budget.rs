@@ -0,0 +1,319 @@
+//! Token budget packing
+//!
+//! When the selected files exceed the configured token budget, the packer
+//! picks files to drop (or truncate) in the order given by the chosen
+//! strategy. Pinned files are never touched.
+
+use crate::core::tokenizer::Tokenizer;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Order in which files are given up when the budget is exceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PackingStrategy {
+    /// Files with the most tokens go first
+    #[default]
+    LargestFirst,
+    /// Files nested deepest in the tree go first
+    DeepestFirst,
+}
+
+impl PackingStrategy {
+    /// All strategies, in display order
+    pub const ALL: [Self; 2] = [Self::LargestFirst, Self::DeepestFirst];
+
+    /// Name used in the config file and output
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::LargestFirst => "largest-first",
+            Self::DeepestFirst => "deepest-first",
+        }
+    }
+
+    /// Parses a config name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == name)
+    }
+
+    /// Human-readable label for the settings UI
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::LargestFirst => "Largest files first",
+            Self::DeepestFirst => "Deepest paths first",
+        }
+    }
+}
+
+/// Token budget settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBudget {
+    /// Whether the budget is enforced
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum number of tokens of the whole export
+    pub max_tokens: usize,
+    /// Which files to give up first
+    #[serde(default)]
+    pub strategy: PackingStrategy,
+    /// Truncate the last file that crosses the budget instead of dropping it
+    #[serde(default)]
+    pub truncate: bool,
+}
+
+impl Default for TokenBudget {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_tokens: 128_000,
+            strategy: PackingStrategy::default(),
+            truncate: false,
+        }
+    }
+}
+
+/// A file considered by the packer
+#[derive(Debug, Clone)]
+pub struct PackItem<'a> {
+    /// Root-relative path
+    pub path: &'a str,
+    /// Token count of the file in the output, contents and header
+    pub tokens: usize,
+    /// Pinned files are always kept
+    pub pinned: bool,
+}
+
+/// What happens to a file after packing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackAction {
+    /// Included unchanged
+    Keep,
+    /// Left out of the output
+    Drop,
+    /// Cut down to roughly this many tokens
+    Truncate(usize),
+}
+
+/// A file that was dropped or truncated to fit the budget
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PackedFile {
+    /// Root-relative path
+    pub path: String,
+    /// Original token count
+    pub tokens: usize,
+    /// Tokens kept after truncation (`None` if dropped)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kept_tokens: Option<usize>,
+}
+
+/// Summary of what the packer changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetReport {
+    /// Configured budget
+    pub max_tokens: usize,
+    /// Strategy used
+    pub strategy: &'static str,
+    /// Tokens of the packed output
+    pub packed_tokens: usize,
+    /// Files left out
+    pub dropped: Vec<PackedFile>,
+    /// Files cut down
+    pub truncated: Vec<PackedFile>,
+}
+
+impl BudgetReport {
+    /// Whether the pinned files alone still exceed the budget
+    #[must_use]
+    pub const fn over_budget(&self) -> bool {
+        self.packed_tokens > self.max_tokens
+    }
+
+    /// One-line summary for notifications
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.dropped.is_empty() {
+            parts.push(format!("dropped {}", Self::list(&self.dropped)));
+        }
+        if !self.truncated.is_empty() {
+            parts.push(format!("truncated {}", Self::list(&self.truncated)));
+        }
+        let mut summary = format!("Token budget {}: {}", self.max_tokens, parts.join("; "));
+        if self.over_budget() {
+            summary.push_str(" (pinned files still exceed the budget)");
+        }
+        summary
+    }
+
+    fn list(files: &[PackedFile]) -> String {
+        const SHOWN: usize = 3;
+        let names: Vec<&str> = files.iter().take(SHOWN).map(|f| f.path.as_str()).collect();
+        if files.len() > SHOWN {
+            format!("{} and {} more", names.join(", "), files.len() - SHOWN)
+        } else {
+            names.join(", ")
+        }
+    }
+}
+
+/// Decides which files to keep, drop or truncate so the total fits the budget
+///
+/// `overhead` is counted against the budget but cannot be reduced (e.g. the
+/// directory tree). Returns one action per item, in the same order.
+#[must_use]
+pub fn plan(items: &[PackItem<'_>], overhead: usize, budget: &TokenBudget) -> Vec<PackAction> {
+    let mut actions = vec![PackAction::Keep; items.len()];
+    let mut total = overhead + items.iter().map(|item| item.tokens).sum::<usize>();
+
+    let mut candidates: Vec<usize> = (0..items.len()).filter(|&i| !items[i].pinned).collect();
+    match budget.strategy {
+        PackingStrategy::LargestFirst => candidates.sort_by(|&a, &b| {
+            items[b]
+                .tokens
+                .cmp(&items[a].tokens)
+                .then_with(|| items[a].path.cmp(items[b].path))
+        }),
+        PackingStrategy::DeepestFirst => candidates.sort_by(|&a, &b| {
+            depth(items[b].path)
+                .cmp(&depth(items[a].path))
+                .then_with(|| items[b].tokens.cmp(&items[a].tokens))
+                .then_with(|| items[a].path.cmp(items[b].path))
+        }),
+    }
+
+    for index in candidates {
+        if total <= budget.max_tokens {
+            break;
+        }
+        let excess = total - budget.max_tokens;
+        let tokens = items[index].tokens;
+
+        if budget.truncate && tokens > excess {
+            actions[index] = PackAction::Truncate(tokens - excess);
+            total -= excess;
+        } else {
+            actions[index] = PackAction::Drop;
+            total -= tokens;
+        }
+    }
+
+    actions
+}
+
+fn depth(path: &str) -> usize {
+    path.split(['/', '\\']).count()
+}
+
+/// Cuts `content` down to whole lines totalling at most `max_tokens`
+///
+/// Returns the kept text followed by a marker noting how many lines were removed.
+#[must_use]
+pub fn truncate_to_tokens(content: &str, max_tokens: usize, tokenizer: &dyn Tokenizer) -> String {
+    let mut kept = String::new();
+    let mut used = 0;
+    let mut lines = content.split_inclusive('\n');
+
+    for line in lines.by_ref() {
+        let tokens = tokenizer.count_tokens(line);
+        if used + tokens > max_tokens {
+            let removed = 1 + lines.count();
+            if !kept.is_empty() && !kept.ends_with('\n') {
+                kept.push('\n');
+            }
+            let _ = writeln!(
+                kept,
+                "[... truncated {removed} lines to fit token budget ...]"
+            );
+            return kept;
+        }
+        used += tokens;
+        kept.push_str(line);
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::tokenizer::CharEstimateTokenizer;
+
+    fn items() -> Vec<PackItem<'static>> {
+        vec![
+            PackItem {
+                path: "README.md",
+                tokens: 500,
+                pinned: false,
+            },
+            PackItem {
+                path: "src/a/deep.rs",
+                tokens: 100,
+                pinned: false,
+            },
+            PackItem {
+                path: "src/big.rs",
+                tokens: 900,
+                pinned: true,
+            },
+            PackItem {
+                path: "src/main.rs",
+                tokens: 300,
+                pinned: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_largest_first_keeps_pinned() {
+        let budget = TokenBudget {
+            enabled: true,
+            max_tokens: 1300,
+            ..TokenBudget::default()
+        };
+        let actions = plan(&items(), 0, &budget);
+        assert_eq!(
+            actions,
+            vec![
+                PackAction::Drop,
+                PackAction::Keep,
+                PackAction::Keep,
+                PackAction::Keep
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deepest_first_and_truncate() {
+        let budget = TokenBudget {
+            enabled: true,
+            max_tokens: 1650,
+            strategy: PackingStrategy::DeepestFirst,
+            truncate: true,
+        };
+        // 1850 tokens against 1650: the deepest file is too small to cover
+        // the excess and is dropped, the next deepest is truncated
+        let actions = plan(&items(), 50, &budget);
+        assert_eq!(actions[1], PackAction::Drop);
+        assert_eq!(actions[3], PackAction::Truncate(200));
+        assert_eq!(actions[0], PackAction::Keep);
+    }
+
+    #[test]
+    fn test_truncate_to_tokens() {
+        let content = "aaaa\nbbbb\ncccc\ndddd\n";
+        let truncated = truncate_to_tokens(content, 4, &CharEstimateTokenizer);
+        assert_eq!(
+            truncated,
+            "aaaa\nbbbb\n[... truncated 2 lines to fit token budget ...]\n"
+        );
+        assert_eq!(
+            truncate_to_tokens(content, 100, &CharEstimateTokenizer),
+            content
+        );
+    }
+}
@@ -0,0 +1,698 @@
+//! Comment stripping for common languages
+//!
+//! A small lexer per language family skips over string literals, so comment
+//! markers inside strings are left alone. Removed comments keep their line
+//! breaks, so every line of the result still corresponds to the same line of
+//! the input. The lexer also reports which lines start inside a multi-line
+//! literal (triple-quoted strings, heredocs, YAML block scalars), since those
+//! lines must survive blank-line collapsing unchanged.
+
+/// A string literal delimiter
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    open: &'static str,
+    close: &'static str,
+    /// Backslash escapes the next character
+    escapes: bool,
+    /// The literal may span lines; otherwise a line break ends it
+    multiline: bool,
+}
+
+impl Quote {
+    const fn new(delimiter: &'static str, escapes: bool, multiline: bool) -> Self {
+        Self {
+            open: delimiter,
+            close: delimiter,
+            escapes,
+            multiline,
+        }
+    }
+}
+
+/// Comment and literal syntax of a language family
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)] // independent syntax features
+struct Syntax {
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    /// Block comments nest (Rust)
+    nested_blocks: bool,
+    /// Checked in order, so triple quotes come before single ones
+    quotes: &'static [Quote],
+    /// Line comments only start at the beginning of a word (shell, YAML)
+    comment_after_space: bool,
+    /// Quotes only open a literal at the start of a value (YAML)
+    quote_at_value_start: bool,
+    /// Raw strings, and char literals told apart from lifetimes (Rust)
+    rust_literals: bool,
+    /// `/.../` regular expression literals (JavaScript, TypeScript)
+    regex_literals: bool,
+    /// `<<EOF` here-documents (shell)
+    heredocs: bool,
+    /// `|` and `>` block scalars (YAML)
+    block_scalars: bool,
+}
+
+const PLAIN: Syntax = Syntax {
+    line_comments: &[],
+    block_comment: None,
+    nested_blocks: false,
+    quotes: &[],
+    comment_after_space: false,
+    quote_at_value_start: false,
+    rust_literals: false,
+    regex_literals: false,
+    heredocs: false,
+    block_scalars: false,
+};
+
+const C_QUOTES: &[Quote] = &[Quote::new("\"", true, false), Quote::new("'", true, false)];
+
+const C_LIKE: Syntax = Syntax {
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: C_QUOTES,
+    ..PLAIN
+};
+
+const TEXT_BLOCKS: Syntax = Syntax {
+    quotes: &[
+        Quote::new("\"\"\"", true, true),
+        Quote::new("\"", true, false),
+        Quote::new("'", true, false),
+    ],
+    ..C_LIKE
+};
+
+const RUST: Syntax = Syntax {
+    nested_blocks: true,
+    quotes: &[Quote::new("\"", true, true)],
+    rust_literals: true,
+    ..C_LIKE
+};
+
+const JAVASCRIPT: Syntax = Syntax {
+    quotes: &[
+        Quote::new("`", true, true),
+        Quote::new("\"", true, false),
+        Quote::new("'", true, false),
+    ],
+    regex_literals: true,
+    ..C_LIKE
+};
+
+const GO: Syntax = Syntax {
+    quotes: &[
+        Quote::new("`", false, true),
+        Quote::new("\"", true, false),
+        Quote::new("'", true, false),
+    ],
+    ..C_LIKE
+};
+
+const CSS: Syntax = Syntax {
+    line_comments: &[],
+    ..C_LIKE
+};
+
+const HASH: Syntax = Syntax {
+    line_comments: &["#"],
+    quotes: C_QUOTES,
+    ..PLAIN
+};
+
+const PYTHON: Syntax = Syntax {
+    quotes: &[
+        Quote::new("\"\"\"", true, true),
+        Quote::new("'''", true, true),
+        Quote::new("\"", true, false),
+        Quote::new("'", true, false),
+    ],
+    ..HASH
+};
+
+const SHELL: Syntax = Syntax {
+    quotes: &[Quote::new("\"", true, true), Quote::new("'", false, true)],
+    comment_after_space: true,
+    heredocs: true,
+    ..HASH
+};
+
+const TOML: Syntax = Syntax {
+    quotes: &[
+        Quote::new("\"\"\"", true, true),
+        Quote::new("'''", false, true),
+        Quote::new("\"", true, false),
+        Quote::new("'", false, false),
+    ],
+    ..HASH
+};
+
+const YAML: Syntax = Syntax {
+    // '' inside a single-quoted scalar closes and reopens it, which lexes the same
+    quotes: &[Quote::new("\"", true, true), Quote::new("'", false, true)],
+    comment_after_space: true,
+    quote_at_value_start: true,
+    block_scalars: true,
+    ..HASH
+};
+
+const SQL: Syntax = Syntax {
+    line_comments: &["--"],
+    block_comment: Some(("/*", "*/")),
+    quotes: &[Quote::new("'", false, true), Quote::new("\"", false, true)],
+    ..PLAIN
+};
+
+/// Returns the syntax for a language name from `get_language_from_extension`
+fn syntax_for(language: &str) -> Option<&'static Syntax> {
+    match language {
+        "rust" => Some(&RUST),
+        "javascript" | "typescript" => Some(&JAVASCRIPT),
+        "go" => Some(&GO),
+        "c" | "cpp" | "csharp" | "php" => Some(&C_LIKE),
+        "java" | "kotlin" | "scala" | "swift" => Some(&TEXT_BLOCKS),
+        "css" => Some(&CSS),
+        "python" => Some(&PYTHON),
+        "ruby" | "r" => Some(&HASH),
+        "bash" => Some(&SHELL),
+        "toml" => Some(&TOML),
+        "yaml" => Some(&YAML),
+        "sql" => Some(&SQL),
+        _ => None,
+    }
+}
+
+/// Result of lexing a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lexed {
+    /// The text, with comments removed if requested
+    pub text: String,
+    /// For every line, whether it starts inside a multi-line literal
+    pub literal_lines: Vec<bool>,
+}
+
+/// Lexes `content` as `language`, removing comments if `remove_comments` is set
+///
+/// Returns `None` for languages without comment support. Shebang lines are
+/// kept.
+#[must_use]
+pub fn lex(content: &str, language: &str, remove_comments: bool) -> Option<Lexed> {
+    let mut lexer = Lexer::new(content, syntax_for(language)?, remove_comments);
+    lexer.run();
+    let Lexer {
+        mut text, literals, ..
+    } = lexer;
+    text.push_str(&content[lexer.copied..]);
+
+    let mut literal_lines = vec![false];
+    let mut literals = literals.into_iter().peekable();
+    for (index, _) in content.match_indices('\n') {
+        let line_start = index + 1;
+        if line_start == content.len() {
+            break;
+        }
+        while literals.next_if(|&(_, end)| end <= line_start).is_some() {}
+        literal_lines.push(
+            literals
+                .peek()
+                .is_some_and(|&(start, _)| start < line_start),
+        );
+    }
+
+    Some(Lexed {
+        text,
+        literal_lines,
+    })
+}
+
+/// Returns `content` with comments blanked to spaces and literals to underscores
+///
+/// Line breaks are kept, so positions in the mask match positions in
+/// `content`. Returns `None` for languages without comment support.
+#[must_use]
+pub fn mask(content: &str, language: &str) -> Option<String> {
+    let mut lexer = Lexer::new(content, syntax_for(language)?, false);
+    lexer.run();
+
+    let mut masked = content.as_bytes().to_vec();
+    let ranges = lexer
+        .comments
+        .iter()
+        .map(|&range| (range, b' '))
+        .chain(lexer.literals.iter().map(|&range| (range, b'_')));
+    for ((start, end), fill) in ranges {
+        for byte in &mut masked[start..end] {
+            if *byte != b'\n' {
+                *byte = fill;
+            }
+        }
+    }
+    // Ranges start and end on character boundaries, so this stays valid UTF-8
+    String::from_utf8(masked).ok()
+}
+
+/// A pending here-document body
+#[derive(Debug)]
+struct Heredoc {
+    terminator: String,
+    /// `<<-` strips leading tabs from the terminator line
+    strip_tabs: bool,
+}
+
+#[derive(Debug)]
+struct Lexer<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    syntax: &'static Syntax,
+    remove_comments: bool,
+    pos: usize,
+    /// End of the input already copied to `text`
+    copied: usize,
+    text: String,
+    /// Byte ranges of literals, in order
+    literals: Vec<(usize, usize)>,
+    /// Byte ranges of comments, in order
+    comments: Vec<(usize, usize)>,
+    heredocs: Vec<Heredoc>,
+    line_start: usize,
+    /// Where a line comment starts on the current line
+    line_comment_at: Option<usize>,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str, syntax: &'static Syntax, remove_comments: bool) -> Self {
+        Self {
+            src,
+            bytes: src.as_bytes(),
+            syntax,
+            remove_comments,
+            pos: 0,
+            copied: 0,
+            text: String::with_capacity(src.len()),
+            literals: Vec::new(),
+            comments: Vec::new(),
+            heredocs: Vec::new(),
+            line_start: 0,
+            line_comment_at: None,
+        }
+    }
+
+    fn run(&mut self) {
+        while self.pos < self.bytes.len() {
+            let rest = &self.src[self.pos..];
+            let byte = self.bytes[self.pos];
+
+            if byte == b'\n' {
+                self.end_of_line();
+                continue;
+            }
+
+            if let Some((open, close)) = self.syntax.block_comment {
+                if rest.starts_with(open) {
+                    let end = self.block_comment_end(self.pos + open.len(), open, close);
+                    self.comment(self.pos, end);
+                    continue;
+                }
+            }
+
+            if self
+                .syntax
+                .line_comments
+                .iter()
+                .any(|marker| rest.starts_with(marker))
+                && self.comment_may_start()
+            {
+                let end = rest.find('\n').map_or(self.src.len(), |end| self.pos + end);
+                self.line_comment_at = Some(self.pos);
+                if self.pos == 0 && rest.starts_with("#!") {
+                    self.pos = end;
+                } else {
+                    self.comment(self.pos, end);
+                }
+                continue;
+            }
+
+            if self.syntax.rust_literals && self.rust_literal() {
+                continue;
+            }
+
+            if self.syntax.regex_literals && byte == b'/' && self.regex_literal() {
+                continue;
+            }
+
+            if let Some(quote) = self
+                .syntax
+                .quotes
+                .iter()
+                .find(|quote| rest.starts_with(quote.open))
+            {
+                if !self.syntax.quote_at_value_start || self.at_value_start() {
+                    let end = self.literal_end(self.pos + quote.open.len(), quote);
+                    self.literal(self.pos, end);
+                    continue;
+                }
+            }
+
+            if self.syntax.heredocs && rest.starts_with("<<") && self.heredoc() {
+                continue;
+            }
+
+            self.pos += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    /// Handles the line break at `pos` and any literal body that follows it
+    fn end_of_line(&mut self) {
+        if self.syntax.block_scalars && self.opens_block_scalar() {
+            let indent = indentation(&self.src[self.line_start..]);
+            let body_start = self.pos + 1;
+            let mut end = body_start;
+            for line in self.src[body_start..].split_inclusive('\n') {
+                if !line.trim().is_empty() && indentation(line) <= indent {
+                    break;
+                }
+                end += line.len();
+            }
+            // The last line break belongs to the line, not the literal
+            let end = if end > body_start && self.bytes[end - 1] == b'\n' {
+                end - 1
+            } else {
+                end
+            };
+            // Ranges start at the line break, so the first body line counts as inside
+            self.literals.push((self.pos, end));
+            self.pos = end;
+            self.start_line(end);
+            return;
+        }
+
+        self.pos += 1;
+        self.start_line(self.pos);
+        let heredocs = std::mem::take(&mut self.heredocs);
+        let count = heredocs.len();
+        for (index, heredoc) in heredocs.into_iter().enumerate() {
+            let body_start = self.pos;
+            let mut end = self.src.len();
+            let mut offset = body_start;
+            for line in self.src[body_start..].split_inclusive('\n') {
+                let trimmed = line.trim_end_matches(['\n', '\r']);
+                let trimmed = if heredoc.strip_tabs {
+                    trimmed.trim_start_matches('\t')
+                } else {
+                    trimmed
+                };
+                if trimmed == heredoc.terminator {
+                    end = offset;
+                    break;
+                }
+                offset += line.len();
+            }
+            self.literals.push((body_start - 1, end));
+            // Continue after the terminator, before its line break unless
+            // another body follows
+            self.pos = self.src[end..]
+                .find('\n')
+                .map_or(self.src.len(), |newline| end + newline);
+            if index + 1 < count && self.pos < self.src.len() {
+                self.pos += 1;
+            }
+            self.start_line(end);
+        }
+    }
+
+    const fn start_line(&mut self, line_start: usize) {
+        self.line_start = line_start;
+        self.line_comment_at = None;
+    }
+
+    /// Whether the line ending at `pos` ends with a YAML block scalar indicator
+    fn opens_block_scalar(&self) -> bool {
+        let code_end = self.line_comment_at.unwrap_or(self.pos);
+        let code = self.src[self.line_start..code_end].trim_end();
+        let code = code.trim_end_matches(|c: char| c == '+' || c == '-' || c.is_ascii_digit());
+        let Some(before) = code.strip_suffix('|').or_else(|| code.strip_suffix('>')) else {
+            return false;
+        };
+        before.is_empty() || before.ends_with([' ', '\t', ':'])
+    }
+
+    /// Whether a line comment marker at `pos` starts a comment
+    fn comment_may_start(&self) -> bool {
+        !self.syntax.comment_after_space
+            || self.pos == 0
+            || matches!(self.bytes[self.pos - 1], b' ' | b'\t' | b'\n' | b'\r')
+    }
+
+    /// Whether a quote at `pos` starts a YAML value
+    fn at_value_start(&self) -> bool {
+        let before = self.src[self.line_start..self.pos].trim_end();
+        before.is_empty() || before.ends_with([':', '-', '[', '{', ',', '?'])
+    }
+
+    fn block_comment_end(&self, from: usize, open: &str, close: &str) -> usize {
+        let mut depth = 1;
+        let mut pos = from;
+        // Steps through bytes, since delimiters are ASCII
+        while pos < self.bytes.len() {
+            let rest = &self.bytes[pos..];
+            if rest.starts_with(close.as_bytes()) {
+                depth -= 1;
+                pos += close.len();
+                if depth == 0 || !self.syntax.nested_blocks {
+                    return pos;
+                }
+            } else if self.syntax.nested_blocks && rest.starts_with(open.as_bytes()) {
+                depth += 1;
+                pos += open.len();
+            } else {
+                pos += 1;
+            }
+        }
+        self.src.len()
+    }
+
+    fn literal_end(&self, from: usize, quote: &Quote) -> usize {
+        let mut pos = from;
+        while pos < self.bytes.len() {
+            let byte = self.bytes[pos];
+            if quote.escapes && byte == b'\\' {
+                pos += 2;
+            } else if self.bytes[pos..].starts_with(quote.close.as_bytes()) {
+                return pos + quote.close.len();
+            } else if byte == b'\n' && !quote.multiline {
+                return pos;
+            } else {
+                pos += 1;
+            }
+        }
+        self.src.len()
+    }
+
+    /// Skips a raw string or char literal at `pos`, telling chars from lifetimes
+    fn rust_literal(&mut self) -> bool {
+        let rest = &self.src[self.pos..];
+        let after_ident = self.pos > 0 && is_ident_byte(self.bytes[self.pos - 1]);
+
+        let raw = rest.strip_prefix("br").or_else(|| rest.strip_prefix('r'));
+        if let Some(raw) = raw.filter(|_| !after_ident) {
+            let hashes = raw.len() - raw.trim_start_matches('#').len();
+            if raw[hashes..].starts_with('"') {
+                let close = format!("\"{}", "#".repeat(hashes));
+                let body = self.pos + (rest.len() - raw.len()) + hashes + 1;
+                let end = self.src[body..]
+                    .find(&close)
+                    .map_or(self.src.len(), |end| body + end + close.len());
+                self.literal(self.pos, end);
+                return true;
+            }
+        }
+
+        if !rest.starts_with('\'') {
+            return false;
+        }
+        let mut chars = rest[1..].chars();
+        let end = match chars.next() {
+            Some('\\') => self.literal_end(self.pos + 1, &Quote::new("'", true, false)),
+            Some(c) if chars.next() == Some('\'') => self.pos + 2 + c.len_utf8(),
+            // A lifetime or label
+            _ => self.pos + 1,
+        };
+        self.literal(self.pos, end);
+        true
+    }
+
+    /// Skips a regular expression literal at `pos`, if one can start here
+    fn regex_literal(&mut self) -> bool {
+        let before = self.src[..self.pos].trim_end();
+        let starts_expression = before.is_empty()
+            || before.ends_with([
+                '(', ',', '=', ':', '[', '!', '&', '|', '?', '{', '}', ';', '+', '-', '*', '%',
+                '<', '>', '~', '^',
+            ])
+            || [
+                "return", "typeof", "case", "do", "else", "in", "of", "yield", "await",
+            ]
+            .iter()
+            .any(|keyword| {
+                before.ends_with(keyword)
+                    && !before[..before.len() - keyword.len()]
+                        .ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$')
+            });
+        if !starts_expression {
+            return false;
+        }
+
+        let mut pos = self.pos + 1;
+        let mut in_class = false;
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'\\' => pos += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'/' if !in_class => {
+                    self.literal(self.pos, pos + 1);
+                    return true;
+                }
+                b'\n' => return false,
+                _ => {}
+            }
+            pos += 1;
+        }
+        false
+    }
+
+    /// Records a here-document started at `pos`, whose body follows the line
+    fn heredoc(&mut self) -> bool {
+        let rest = &self.src[self.pos + 2..];
+        if rest.starts_with('<') || (self.pos > 0 && self.bytes[self.pos - 1] == b'<') {
+            return false;
+        }
+        let strip_tabs = rest.starts_with('-');
+        let rest = rest.strip_prefix('-').unwrap_or(rest);
+        let spec = rest.trim_start_matches([' ', '\t']);
+        let quote = spec.chars().next().filter(|c| *c == '\'' || *c == '"');
+        let name = quote.map_or(spec, |q| &spec[q.len_utf8()..]);
+        let len = name.len()
+            - name
+                .trim_start_matches(|c: char| c.is_alphanumeric() || c == '_')
+                .len();
+        if len == 0 || name.starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+
+        self.heredocs.push(Heredoc {
+            terminator: name[..len].to_string(),
+            strip_tabs,
+        });
+        let mut end = self.src.len() - name.len() + len;
+        if quote.is_some_and(|q| self.src[end..].starts_with(q)) {
+            end += 1;
+        }
+        self.pos = end;
+        true
+    }
+
+    fn literal(&mut self, start: usize, end: usize) {
+        self.literals.push((start, end));
+        self.pos = end;
+    }
+
+    /// Drops the comment at `start..end`, keeping its line breaks
+    fn comment(&mut self, start: usize, end: usize) {
+        self.comments.push((start, end));
+        self.pos = end;
+        if !self.remove_comments {
+            return;
+        }
+
+        self.text.push_str(&self.src[self.copied..start]);
+        let trimmed_len = self.text.trim_end_matches([' ', '\t']).len();
+        let ends_line = end == self.src.len() || matches!(self.bytes[end], b'\n' | b'\r');
+        let line_breaks = self.src[start..end].matches('\n').count();
+        if ends_line || line_breaks > 0 {
+            self.text.truncate(trimmed_len);
+        }
+        for _ in 0..line_breaks {
+            self.text.push('\n');
+        }
+        // Keep `a/* x */b` from gluing two tokens together
+        if !ends_line
+            && !self.text.ends_with(char::is_whitespace)
+            && !self.src[end..].starts_with(char::is_whitespace)
+        {
+            self.text.push(' ');
+        }
+        self.copied = end;
+    }
+}
+
+const fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strip(content: &str, language: &str) -> String {
+        lex(content, language, true).unwrap().text
+    }
+
+    #[test]
+    fn test_strip_respects_string_literals() {
+        assert_eq!(
+            strip(
+                "let url = \"http://x\"; // note\n/* outer /* inner */ */let c = '\"'; // x\nfn f<'a>(s: &'a str) {}\nlet r = r#\"/* kept */\"#;\n",
+                "rust"
+            ),
+            "let url = \"http://x\";\nlet c = '\"';\nfn f<'a>(s: &'a str) {}\nlet r = r#\"/* kept */\"#;\n"
+        );
+        assert_eq!(
+            strip(
+                "#!/usr/bin/env python\ns = '# not' # comment\n\"\"\"doc # kept\n\"\"\"\n",
+                "python"
+            ),
+            "#!/usr/bin/env python\ns = '# not'\n\"\"\"doc # kept\n\"\"\"\n"
+        );
+        assert_eq!(
+            strip(
+                "const re = /\\/\\/[\"]/g; // c\nconst t = `a // b`;\nx = a/*gap*/b;\n",
+                "javascript"
+            ),
+            "const re = /\\/\\/[\"]/g;\nconst t = `a // b`;\nx = a b;\n"
+        );
+        assert_eq!(
+            strip(
+                "echo $# ${#x} 'a # b' # c\ncat <<-EOF\n\t# body\n\tEOF\n",
+                "bash"
+            ),
+            "echo $# ${#x} 'a # b'\ncat <<-EOF\n\t# body\n\tEOF\n"
+        );
+        assert_eq!(
+            strip("key = \"a#b\" # c\nraw = '''\n# kept\n'''\n", "toml"),
+            "key = \"a#b\"\nraw = '''\n# kept\n'''\n"
+        );
+        assert_eq!(
+            strip(
+                "a: don't # c\nb: |\n  # kept\n  x\nc: 'x # y' # z\n",
+                "yaml"
+            ),
+            "a: don't\nb: |\n  # kept\n  x\nc: 'x # y'\n"
+        );
+        assert!(lex("<!-- x -->", "html", true).is_none());
+    }
+
+    #[test]
+    fn test_literal_lines() {
+        let lexed = lex("a = \"\"\"\n\n\n\"\"\"\nb = 1\n", "python", true).unwrap();
+        assert_eq!(lexed.literal_lines, vec![false, true, true, true, false]);
+
+        let lexed = lex("cat <<EOF\n\nEOF\necho\n", "bash", false).unwrap();
+        assert_eq!(lexed.literal_lines, vec![false, true, false, false]);
+    }
+}
@@ -0,0 +1,147 @@
+//! Searching file contents for text or a regular expression
+//!
+//! Every match counts as a hit; the first few matching lines of a file are
+//! kept as a preview, trimmed and shortened so a minified file cannot flood
+//! the results list.
+
+use regex::{Regex, RegexBuilder};
+use std::path::PathBuf;
+
+/// Matching lines kept as a preview per file
+pub const PREVIEW_LINES: usize = 3;
+
+/// Characters of a preview line shown before it is cut off
+const PREVIEW_WIDTH: usize = 160;
+
+/// Compiled content search
+#[derive(Debug, Clone)]
+pub struct ContentQuery {
+    regex: Regex,
+}
+
+/// A line of a file containing a match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewLine {
+    /// 1-based line number
+    pub number: usize,
+    /// Trimmed text of the line
+    pub text: String,
+}
+
+/// Matches of a search in one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentMatch {
+    /// Absolute path of the file
+    pub path: PathBuf,
+    /// Number of matches in the file
+    pub hits: usize,
+    /// The first matching lines
+    pub preview: Vec<PreviewLine>,
+}
+
+impl ContentQuery {
+    /// Compiles a search for `text`, taken literally unless `regex` is set
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the text is empty or not a valid regular expression
+    pub fn new(text: &str, regex: bool, case_sensitive: bool) -> Result<Self, String> {
+        if text.is_empty() {
+            return Err("Empty search".to_string());
+        }
+        let pattern = if regex {
+            text.to_string()
+        } else {
+            regex::escape(text)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!case_sensitive)
+            .multi_line(true)
+            .build()
+            .map(|regex| Self { regex })
+            .map_err(|e| format!("Invalid regex: {e}"))
+    }
+
+    /// Searches `content`, returning the hit count and preview lines if anything matches
+    #[must_use]
+    pub fn search(&self, content: &str) -> Option<(usize, Vec<PreviewLine>)> {
+        let mut hits = 0;
+        let mut preview: Vec<PreviewLine> = Vec::new();
+        // Line number of the end of the previous match, counted incrementally
+        let mut line = 1;
+        let mut line_start = 0;
+        let mut scanned = 0;
+        for found in self.regex.find_iter(content) {
+            hits += 1;
+            if preview.len() == PREVIEW_LINES {
+                continue;
+            }
+            for (offset, _) in content[scanned..found.start()].match_indices('\n') {
+                line += 1;
+                line_start = scanned + offset + 1;
+            }
+            scanned = found.start();
+            if preview.last().is_some_and(|last| last.number == line) {
+                continue;
+            }
+            let line_end = content[line_start..]
+                .find('\n')
+                .map_or(content.len(), |end| line_start + end);
+            let text = content[line_start..line_end].trim();
+            let text = match text.char_indices().nth(PREVIEW_WIDTH) {
+                Some((cut, _)) => format!("{}…", &text[..cut]),
+                None => text.to_string(),
+            };
+            preview.push(PreviewLine { number: line, text });
+        }
+        (hits > 0).then_some((hits, preview))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_search() {
+        let content = "fn main() {\n    let token = get_token();\n    send(token, TOKEN);\n}\n\n// token\n// token\n";
+
+        let literal = ContentQuery::new("token", false, true).unwrap();
+        let (hits, preview) = literal.search(content).unwrap();
+        assert_eq!(hits, 5);
+        assert_eq!(
+            preview,
+            vec![
+                PreviewLine {
+                    number: 2,
+                    text: "let token = get_token();".to_string()
+                },
+                PreviewLine {
+                    number: 3,
+                    text: "send(token, TOKEN);".to_string()
+                },
+                PreviewLine {
+                    number: 6,
+                    text: "// token".to_string()
+                },
+            ]
+        );
+
+        let insensitive = ContentQuery::new("token", false, false).unwrap();
+        assert_eq!(insensitive.search(content).unwrap().0, 6);
+
+        // Literal searches escape regex syntax
+        let call = ContentQuery::new("get_token()", false, true).unwrap();
+        assert_eq!(call.search(content).unwrap().0, 1);
+        let regex = ContentQuery::new(r"^fn \w+", true, true).unwrap();
+        assert_eq!(regex.search(content).unwrap().1[0].number, 1);
+
+        assert!(literal.search("nothing here").is_none());
+        assert!(ContentQuery::new("", false, true).is_err());
+        assert!(ContentQuery::new("(", true, true).is_err());
+
+        let long = format!("{}token", "x".repeat(500));
+        let (_, preview) = literal.search(&long).unwrap();
+        assert!(preview[0].text.ends_with('…'));
+    }
+}
@@ -0,0 +1,306 @@
+//! Decoding file contents that are not plain UTF-8
+//!
+//! A byte order mark selects UTF-8 or UTF-16. Without one, UTF-8 is tried
+//! first and the configured single-byte encoding is used when it fails. Lossy
+//! replacement is only used when nothing else applies, and is reported so the
+//! file can be flagged.
+
+use serde::{Deserialize, Serialize};
+
+/// Encoding a file was decoded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextEncoding {
+    /// UTF-8, with or without a byte order mark
+    Utf8,
+    /// UTF-16 little endian (BOM `FF FE`)
+    Utf16Le,
+    /// UTF-16 big endian (BOM `FE FF`)
+    Utf16Be,
+    /// Windows-1252, a superset of the printable Latin-1 range
+    Windows1252,
+    /// ISO-8859-1 (Latin-1)
+    Latin1,
+}
+
+impl TextEncoding {
+    /// Label used in output attributes
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+            Self::Windows1252 => "windows-1252",
+            Self::Latin1 => "iso-8859-1",
+        }
+    }
+}
+
+/// Single-byte encoding tried when a file without a BOM is not valid UTF-8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FallbackEncoding {
+    /// Windows-1252
+    #[default]
+    Windows1252,
+    /// ISO-8859-1 (Latin-1)
+    Latin1,
+    /// No fallback: the file fails unless lossy decoding is enabled
+    None,
+}
+
+impl FallbackEncoding {
+    /// All fallbacks, in display order
+    pub const ALL: [Self; 3] = [Self::Windows1252, Self::Latin1, Self::None];
+
+    /// Name used in the config file and on the command line
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Windows1252 => "windows-1252",
+            Self::Latin1 => "latin-1",
+            Self::None => "none",
+        }
+    }
+
+    /// Parses a config name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|fallback| fallback.name() == name)
+    }
+
+    /// Human-readable label for the settings UI
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Windows1252 => "Windows-1252",
+            Self::Latin1 => "Latin-1 (ISO-8859-1)",
+            Self::None => "None (UTF-8 only)",
+        }
+    }
+}
+
+/// How file contents are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EncodingConfig {
+    /// Encoding tried when a file is not valid UTF-8
+    #[serde(default)]
+    pub fallback: FallbackEncoding,
+    /// Replace undecodable bytes with U+FFFD instead of failing the file
+    #[serde(default)]
+    pub lossy: bool,
+    /// Convert CRLF line endings to LF
+    #[serde(default)]
+    pub normalize_line_endings: bool,
+}
+
+/// Decoded file contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoded {
+    /// The text
+    pub text: String,
+    /// Encoding it was decoded from
+    pub encoding: TextEncoding,
+    /// Whether undecodable bytes were replaced with U+FFFD
+    pub lossy: bool,
+}
+
+/// Returns the encoding announced by a byte order mark and the mark's length
+#[must_use]
+pub fn detect_bom(bytes: &[u8]) -> Option<(TextEncoding, usize)> {
+    if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+        Some((TextEncoding::Utf8, 3))
+    } else if bytes.starts_with(&[0xff, 0xfe]) {
+        Some((TextEncoding::Utf16Le, 2))
+    } else if bytes.starts_with(&[0xfe, 0xff]) {
+        Some((TextEncoding::Utf16Be, 2))
+    } else {
+        None
+    }
+}
+
+/// Decodes file contents according to `config`
+///
+/// # Errors
+///
+/// Returns an error if the bytes are not valid in the detected encoding and
+/// lossy decoding is disabled
+pub fn decode(mut bytes: Vec<u8>, config: &EncodingConfig) -> Result<Decoded, String> {
+    let (encoding, bom_len) = detect_bom(&bytes).unwrap_or((TextEncoding::Utf8, 0));
+    bytes.drain(..bom_len);
+
+    let (text, encoding, lossy) = match encoding {
+        TextEncoding::Utf16Le | TextEncoding::Utf16Be => {
+            let (text, lossy) = decode_utf16(&bytes, encoding == TextEncoding::Utf16Be);
+            if lossy && !config.lossy {
+                return Err(format!("invalid {} data", encoding.name()));
+            }
+            (text, encoding, lossy)
+        }
+        _ => match String::from_utf8(bytes) {
+            Ok(text) => (text, TextEncoding::Utf8, false),
+            Err(e) => {
+                // Only files without a BOM fall back to a single-byte encoding
+                let fallback = if bom_len == 0 {
+                    config.fallback
+                } else {
+                    FallbackEncoding::None
+                };
+                match fallback {
+                    FallbackEncoding::Windows1252 => (
+                        decode_single_byte(e.as_bytes(), windows_1252_char),
+                        TextEncoding::Windows1252,
+                        false,
+                    ),
+                    FallbackEncoding::Latin1 => (
+                        decode_single_byte(e.as_bytes(), char::from),
+                        TextEncoding::Latin1,
+                        false,
+                    ),
+                    FallbackEncoding::None if config.lossy => (
+                        String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                        TextEncoding::Utf8,
+                        true,
+                    ),
+                    FallbackEncoding::None => return Err(e.to_string()),
+                }
+            }
+        },
+    };
+
+    let text = if config.normalize_line_endings && text.contains("\r\n") {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    };
+
+    Ok(Decoded {
+        text,
+        encoding,
+        lossy,
+    })
+}
+
+/// Decodes UTF-16 code units, replacing unpaired surrogates and a trailing odd byte
+///
+/// Returns the text and whether anything was replaced.
+fn decode_utf16(bytes: &[u8], big_endian: bool) -> (String, bool) {
+    let units = bytes.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if big_endian {
+            u16::from_be_bytes(pair)
+        } else {
+            u16::from_le_bytes(pair)
+        }
+    });
+
+    let odd = !bytes.len().is_multiple_of(2);
+    let mut lossy = odd;
+    let mut text: String = char::decode_utf16(units)
+        .map(|unit| {
+            unit.unwrap_or_else(|_| {
+                lossy = true;
+                char::REPLACEMENT_CHARACTER
+            })
+        })
+        .collect();
+    if odd {
+        text.push(char::REPLACEMENT_CHARACTER);
+    }
+    (text, lossy)
+}
+
+fn decode_single_byte(bytes: &[u8], map: impl Fn(u8) -> char) -> String {
+    bytes.iter().map(|&byte| map(byte)).collect()
+}
+
+/// Maps a Windows-1252 byte to its character
+///
+/// Bytes 0x80-0x9F differ from Latin-1; the five unassigned ones map to the
+/// matching C1 control character, as browsers do.
+fn windows_1252_char(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bom_and_utf16() {
+        let config = EncodingConfig::default();
+
+        let utf8 = decode(b"\xef\xbb\xbfh\xc3\xa9".to_vec(), &config).unwrap();
+        assert_eq!(
+            (utf8.text.as_str(), utf8.encoding),
+            ("hé", TextEncoding::Utf8)
+        );
+
+        let mut le = vec![0xff, 0xfe];
+        le.extend("hé\r\n".encode_utf16().flat_map(u16::to_le_bytes));
+        let le = decode(le, &config).unwrap();
+        assert_eq!(
+            (le.text.as_str(), le.encoding),
+            ("hé\r\n", TextEncoding::Utf16Le)
+        );
+
+        let mut be = vec![0xfe, 0xff];
+        be.extend("ok".encode_utf16().flat_map(u16::to_be_bytes));
+        assert_eq!(decode(be, &config).unwrap().text, "ok");
+
+        // Unpaired surrogate
+        let broken = vec![0xff, 0xfe, 0x00, 0xd8, b'a', 0x00];
+        assert!(decode(broken.clone(), &config).is_err());
+        let lossy = EncodingConfig {
+            lossy: true,
+            ..config
+        };
+        let decoded = decode(broken, &lossy).unwrap();
+        assert_eq!(decoded.text, "\u{fffd}a");
+        assert!(decoded.lossy);
+    }
+
+    #[test]
+    fn test_single_byte_fallback() {
+        let bytes = b"caf\xe9 \x93quoted\x94\r\n".to_vec();
+
+        let cp1252 = decode(bytes.clone(), &EncodingConfig::default()).unwrap();
+        assert_eq!(cp1252.text, "café “quoted”\r\n");
+        assert_eq!(cp1252.encoding, TextEncoding::Windows1252);
+
+        let latin1 = EncodingConfig {
+            fallback: FallbackEncoding::Latin1,
+            normalize_line_endings: true,
+            ..EncodingConfig::default()
+        };
+        let decoded = decode(bytes.clone(), &latin1).unwrap();
+        assert_eq!(decoded.text, "café \u{93}quoted\u{94}\n");
+        assert_eq!(decoded.encoding, TextEncoding::Latin1);
+
+        let strict = EncodingConfig {
+            fallback: FallbackEncoding::None,
+            ..EncodingConfig::default()
+        };
+        assert!(decode(bytes.clone(), &strict).is_err());
+        let lossy = decode(
+            bytes,
+            &EncodingConfig {
+                lossy: true,
+                ..strict
+            },
+        )
+        .unwrap();
+        assert!(lossy.lossy);
+        assert!(lossy.text.starts_with("caf\u{fffd} "));
+    }
+}
@@ -0,0 +1,114 @@
+//! Git context section of the output
+//!
+//! The section holds the current branch, the HEAD commit, the most recent
+//! commit subjects and a unified diff limited to the selected files, so a
+//! review request can carry the change and the full files in one paste. The
+//! data is read from the repository by `utils::git::read_context`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Git context settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GitContextConfig {
+    /// Whether the git section is added to the output
+    pub enabled: bool,
+    /// Number of recent commit subjects listed
+    pub commit_count: usize,
+    /// Ref the diff starts from, diffing `base...HEAD`; empty diffs the working tree against HEAD
+    pub diff_base: String,
+}
+
+impl Default for GitContextConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            commit_count: 5,
+            diff_base: String::new(),
+        }
+    }
+}
+
+/// A commit listed in the git section
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommitSummary {
+    /// Full commit hash
+    pub hash: String,
+    /// First line of the commit message
+    pub subject: String,
+}
+
+impl CommitSummary {
+    /// Abbreviated hash used in commit lists
+    #[must_use]
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(7)]
+    }
+}
+
+/// Repository state written to the git section
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize)]
+pub struct GitContext {
+    /// Current branch, or `None` when HEAD is detached
+    pub branch: Option<String>,
+    /// HEAD commit, or `None` before the first commit
+    pub head: Option<CommitSummary>,
+    /// Most recent commits, newest first, starting with HEAD
+    pub recent_commits: Vec<CommitSummary>,
+    /// Ref the diff starts from, or `None` for the working tree diff
+    pub diff_base: Option<String>,
+    /// Unified diff of the selected files
+    pub diff: String,
+}
+
+impl GitContext {
+    /// Describes what the diff compares, e.g. "main...HEAD"
+    #[must_use]
+    pub fn diff_range(&self) -> String {
+        self.diff_base.as_ref().map_or_else(
+            || "working tree".to_string(),
+            |base| format!("{base}...HEAD"),
+        )
+    }
+
+    /// Plain-text summary lines: branch, HEAD and recent commits
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "Branch: {}",
+            self.branch.as_deref().unwrap_or("(detached HEAD)")
+        );
+        match &self.head {
+            Some(head) => {
+                let _ = writeln!(text, "HEAD: {} {}", head.hash, head.subject);
+            }
+            None => text.push_str("HEAD: (no commits)\n"),
+        }
+        if !self.recent_commits.is_empty() {
+            text.push_str("\nRecent commits:\n");
+            for commit in &self.recent_commits {
+                let _ = writeln!(text, "- {} {}", commit.short_hash(), commit.subject);
+            }
+        }
+        text
+    }
+}
+
+impl std::fmt::Display for GitContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.summary())?;
+        if self.diff.is_empty() {
+            writeln!(
+                f,
+                "\nNo changes to the selected files ({})",
+                self.diff_range()
+            )
+        } else {
+            writeln!(f, "\nDiff ({}):", self.diff_range())?;
+            f.write_str(&self.diff)
+        }
+    }
+}
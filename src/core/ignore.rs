@@ -0,0 +1,182 @@
+//! Ignore patterns with gitignore semantics
+//!
+//! User patterns are written like the lines of a `.gitignore` and matched
+//! against paths relative to the opened root:
+//! - a pattern without a `/` matches a name at any depth (`target`, `*.log`)
+//! - a leading or inner `/` anchors it to the root (`/build`, `src/generated/*.rs`)
+//! - `**` spans any number of directories (`docs/**/*.png`)
+//! - a trailing `/` only matches directories (`cache/`)
+//! - a leading `!` re-includes what an earlier pattern excluded (`!keep.log`)
+//!
+//! As in git, the last matching pattern decides. Callers walk top-down and
+//! never descend into ignored directories, so a file cannot be re-included
+//! once its parent directory is excluded.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::{Path, PathBuf};
+
+/// Compiled set of ignore patterns for one root
+#[derive(Debug, Clone)]
+pub struct IgnorePatterns {
+    /// Directory the patterns are relative to
+    root: PathBuf,
+    /// The compiled patterns
+    matcher: Gitignore,
+    /// Patterns that failed to compile, with the reason
+    invalid: Vec<String>,
+}
+
+impl IgnorePatterns {
+    /// Compiles `patterns` relative to `root`
+    ///
+    /// Blank lines and `#` comments are skipped. Invalid patterns are left
+    /// out and reported by [`Self::invalid`].
+    #[must_use]
+    pub fn new(root: &Path, patterns: &[String]) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        let mut invalid = Vec::new();
+        for pattern in patterns {
+            if let Err(e) = builder.add_line(None, pattern.trim()) {
+                invalid.push(format!("{}: {e}", pattern.trim()));
+            }
+        }
+        let matcher = builder.build().unwrap_or_else(|e| {
+            invalid.push(e.to_string());
+            Gitignore::empty()
+        });
+        Self {
+            root: root.to_path_buf(),
+            matcher,
+            invalid,
+        }
+    }
+
+    /// Patterns that match nothing
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            root: PathBuf::new(),
+            matcher: Gitignore::empty(),
+            invalid: Vec::new(),
+        }
+    }
+
+    /// Returns whether there are no patterns
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.matcher.is_empty()
+    }
+
+    /// Patterns that were left out because they failed to compile
+    #[must_use]
+    pub fn invalid(&self) -> &[String] {
+        &self.invalid
+    }
+
+    /// Returns the decision of the last pattern matching `path`
+    ///
+    /// `Some(true)` means excluded, `Some(false)` re-included by a `!`
+    /// pattern, and `None` that no pattern matched. A path outside the root
+    /// is matched by its name alone.
+    #[must_use]
+    pub fn decide(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => Path::new(path.file_name()?),
+        };
+        match self.matcher.matched(relative, is_dir) {
+            Match::Ignore(_) => Some(true),
+            Match::Whitelist(_) => Some(false),
+            Match::None => None,
+        }
+    }
+
+    /// Returns whether `path` is excluded
+    #[must_use]
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.decide(path, is_dir) == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(lines: &[&str]) -> IgnorePatterns {
+        let lines: Vec<String> = lines.iter().map(ToString::to_string).collect();
+        IgnorePatterns::new(Path::new("/project"), &lines)
+    }
+
+    fn file(patterns: &IgnorePatterns, path: &str) -> bool {
+        patterns.is_ignored(&Path::new("/project").join(path), false)
+    }
+
+    fn dir(patterns: &IgnorePatterns, path: &str) -> bool {
+        patterns.is_ignored(&Path::new("/project").join(path), true)
+    }
+
+    #[test]
+    fn test_gitignore_semantics() {
+        // Unanchored names match at any depth
+        let names = patterns(&["target", "*.log", ".*"]);
+        assert!(dir(&names, "target"));
+        assert!(dir(&names, "crates/core/target"));
+        assert!(file(&names, "logs/app.log"));
+        assert!(file(&names, ".env"));
+        assert!(!file(&names, "targets.rs"));
+        assert!(!file(&names, "log.rs"));
+
+        // A leading slash anchors to the root
+        let anchored = patterns(&["/build"]);
+        assert!(dir(&anchored, "build"));
+        assert!(!dir(&anchored, "src/build"));
+
+        // An inner slash anchors too, and `*` stops at separators
+        let nested = patterns(&["src/generated/*.rs"]);
+        assert!(file(&nested, "src/generated/schema.rs"));
+        assert!(!file(&nested, "src/generated/v2/schema.rs"));
+        assert!(!file(&nested, "lib/src/generated/schema.rs"));
+        assert!(!file(&nested, "src/generated/schema.ts"));
+
+        // `**` spans zero or more directories
+        let globstar = patterns(&["docs/**/*.png", "**/fixtures"]);
+        assert!(file(&globstar, "docs/logo.png"));
+        assert!(file(&globstar, "docs/guide/img/step.png"));
+        assert!(!file(&globstar, "docs.png"));
+        assert!(!file(&globstar, "site/docs/logo.png"));
+        assert!(dir(&globstar, "fixtures"));
+        assert!(dir(&globstar, "tests/data/fixtures"));
+
+        // A trailing slash only matches directories
+        let dirs_only = patterns(&["cache/"]);
+        assert!(dir(&dirs_only, "cache"));
+        assert!(!file(&dirs_only, "cache"));
+
+        // Negation re-includes, and the last matching pattern wins
+        let negated = patterns(&["*.log", "!keep.log"]);
+        assert!(file(&negated, "app.log"));
+        assert!(!file(&negated, "keep.log"));
+        assert_eq!(
+            negated.decide(Path::new("/project/logs/keep.log"), false),
+            Some(false)
+        );
+        assert_eq!(negated.decide(Path::new("/project/main.rs"), false), None);
+        let reordered = patterns(&["!keep.log", "*.log"]);
+        assert!(file(&reordered, "keep.log"));
+
+        // Escapes, comments and blank lines
+        let escaped = patterns(&["\\!important.txt", "# comment", "", "  "]);
+        assert!(file(&escaped, "!important.txt"));
+        assert!(!file(&escaped, "# comment"));
+
+        // Outside the root only the name is matched
+        assert!(names.is_ignored(Path::new("/elsewhere/debug.log"), false));
+
+        // Invalid patterns are reported and skipped
+        let invalid = patterns(&["src/[", "*.tmp"]);
+        assert_eq!(invalid.invalid().len(), 1);
+        assert!(file(&invalid, "a.tmp"));
+        assert!(IgnorePatterns::empty().is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+//! Line-numbered file contents
+//!
+//! Numbers are added right after a file is read and transformed, before any
+//! truncation or splitting, so kept lines carry their original numbers and the
+//! truncation markers inserted later stay unnumbered. A large file of which
+//! only the first and last lines were read is numbered with their lines on
+//! disk through [`LineNumbers::apply_numbered`].
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Separator between a line number and the line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineSeparator {
+    /// A tab character
+    #[default]
+    Tab,
+    /// A `|` surrounded by spaces
+    Pipe,
+}
+
+impl LineSeparator {
+    /// All separators, in display order
+    pub const ALL: [Self; 2] = [Self::Tab, Self::Pipe];
+
+    /// Name used in the config file and on the command line
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Tab => "tab",
+            Self::Pipe => "pipe",
+        }
+    }
+
+    /// Parses a config name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|separator| separator.name() == name)
+    }
+
+    /// Human-readable label for the settings UI
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Tab => "Tab",
+            Self::Pipe => "|",
+        }
+    }
+
+    /// Text placed between the number and the line
+    #[must_use]
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tab => "\t",
+            Self::Pipe => " | ",
+        }
+    }
+}
+
+/// Line numbering settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineNumbers {
+    /// Whether content lines are numbered
+    #[serde(default)]
+    pub enabled: bool,
+    /// Minimum width numbers are right-aligned to
+    pub width: usize,
+    /// Separator between number and line
+    #[serde(default)]
+    pub separator: LineSeparator,
+}
+
+impl Default for LineNumbers {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            width: 4,
+            separator: LineSeparator::default(),
+        }
+    }
+}
+
+impl LineNumbers {
+    /// Prefixes every line of `content` with its 1-based line number
+    ///
+    /// Numbers wider than the configured width widen the column for the whole
+    /// file, so it stays aligned.
+    #[must_use]
+    pub fn apply(&self, content: &str) -> String {
+        let line_count = content.split_inclusive('\n').count();
+        self.write_numbered(content, 1..=line_count, line_count)
+    }
+
+    /// Prefixes the lines of `content` with the given numbers
+    ///
+    /// Used for transformed contents, whose lines keep the numbers they had in
+    /// the original file.
+    #[must_use]
+    pub fn apply_numbered(&self, content: &str, line_numbers: &[usize]) -> String {
+        let last = line_numbers.iter().copied().max().unwrap_or_default();
+        self.write_numbered(content, line_numbers.iter().copied(), last)
+    }
+
+    fn write_numbered(
+        &self,
+        content: &str,
+        numbers: impl Iterator<Item = usize>,
+        last: usize,
+    ) -> String {
+        let width = self.width.max(last.to_string().len());
+        let separator = self.separator.as_str();
+
+        let mut numbered = String::with_capacity(content.len() * 2);
+        for (number, line) in numbers.zip(content.split_inclusive('\n')) {
+            let _ = write!(numbered, "{number:>width$}{separator}{line}");
+        }
+        numbered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_line_numbers() {
+        let tab = LineNumbers {
+            enabled: true,
+            ..LineNumbers::default()
+        };
+        assert_eq!(tab.apply("a\n\nb"), "   1\ta\n   2\t\n   3\tb");
+        assert_eq!(tab.apply(""), "");
+
+        let pipe = LineNumbers {
+            enabled: true,
+            width: 1,
+            separator: LineSeparator::Pipe,
+        };
+        let content = "x\n".repeat(10);
+        let numbered = pipe.apply(&content);
+        assert!(numbered.starts_with(" 1 | x\n"));
+        assert!(numbered.ends_with("10 | x\n"));
+    }
+}
@@ -1,3 +1,20 @@
 //! Core business logic and data models for fsPrompt
 
+pub mod budget;
+pub mod comments;
+pub mod content_search;
+pub mod encoding;
+pub mod git_context;
+pub mod ignore;
+pub mod line_numbers;
+pub mod outline;
+pub mod query;
+pub mod rust_deps;
+pub mod secrets;
+pub mod size_cap;
+pub mod split;
+pub mod template;
+pub mod tokenizer;
+pub mod transform;
 pub mod types;
+pub mod xml;
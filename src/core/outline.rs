@@ -0,0 +1,442 @@
+//! Outline mode: keeps declarations and collapses function bodies
+//!
+//! Works on the comment and literal mask from the lexer, so braces and
+//! keywords inside strings or comments never count. Brace languages keep
+//! everything outside function bodies and replace each body with `{ ... }`.
+//! Python keeps signatures and docstrings and replaces the rest of each body
+//! with `...`. Everything the outline keeps is copied verbatim, including doc
+//! comments and attributes.
+
+use crate::core::comments;
+use crate::core::transform::Transformed;
+
+/// Words that start a function whose body is collapsed
+const FUNCTION_WORDS: &[&str] = &["fn", "func", "function", "fun", "def"];
+
+/// Words that start a declaration whose members are outlined in turn
+const CONTAINER_WORDS: &[&str] = &[
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "impl",
+    "mod",
+    "class",
+    "interface",
+    "namespace",
+    "extern",
+    "object",
+    "record",
+    "protocol",
+    "extension",
+    "module",
+];
+
+/// Words that start a block that is not a function body
+const CONTROL_WORDS: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "switch",
+    "try",
+    "catch",
+    "finally",
+    "with",
+    "synchronized",
+    "lock",
+    "using",
+];
+
+/// Outlines `content`, written in `language`
+///
+/// `language` is a name from `get_language_from_extension`. Returns `None`
+/// for unsupported languages and when the outline would not change anything.
+#[must_use]
+pub fn outline(content: &str, language: &str) -> Option<Transformed> {
+    let mut builder = Builder::new(content);
+    match language {
+        "python" => outline_python(content, &mut builder)?,
+        "rust" | "go" | "php" | "swift" | "kotlin" | "scala" => {
+            outline_braces(content, language, true, &mut builder)?;
+        }
+        "javascript" | "typescript" | "c" | "cpp" | "csharp" | "java" => {
+            outline_braces(content, language, false, &mut builder)?;
+        }
+        _ => return None,
+    }
+
+    let transformed = Transformed {
+        text: builder.text,
+        line_numbers: builder.line_numbers,
+        outlined: true,
+    };
+    (transformed.text != content).then_some(transformed)
+}
+
+/// Collapses function bodies in a language with braces
+///
+/// With `keywords_only`, only blocks after a function keyword or `=>` count
+/// as bodies. Otherwise a block after a parameter list also counts, which
+/// catches methods in languages without a function keyword.
+fn outline_braces(
+    content: &str,
+    language: &str,
+    keywords_only: bool,
+    builder: &mut Builder<'_>,
+) -> Option<()> {
+    let masked = comments::mask(content, language)?;
+    let bytes = masked.as_bytes();
+
+    let mut header_start = 0;
+    let mut nesting = 0usize;
+    let mut copied = 0;
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'(' | b'[' => nesting += 1,
+            b')' | b']' => nesting = nesting.saturating_sub(1),
+            b';' if nesting == 0 => header_start = pos + 1,
+            b'}' => {
+                header_start = pos + 1;
+                nesting = 0;
+            }
+            b'{' => {
+                let header = &masked[header_start..pos];
+                header_start = pos + 1;
+                nesting = 0;
+                if is_function_header(header, keywords_only) {
+                    if let Some(close) = matching_brace(bytes, pos) {
+                        if !masked[pos + 1..close].trim().is_empty() {
+                            builder.copy(copied, pos + 1);
+                            builder.insert(" ... ", pos + 1);
+                            copied = close;
+                            // The closing brace ends the header on the next pass
+                            pos = close;
+                            continue;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        pos += 1;
+    }
+    builder.copy(copied, content.len());
+    Some(())
+}
+
+/// Returns whether the block after `header` is a function body
+fn is_function_header(header: &str, keywords_only: bool) -> bool {
+    let words = header
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|word| !word.is_empty());
+    let mut other_block = false;
+    for word in words {
+        if FUNCTION_WORDS.contains(&word) {
+            return true;
+        }
+        other_block |= CONTAINER_WORDS.contains(&word) || CONTROL_WORDS.contains(&word);
+    }
+    if other_block {
+        return false;
+    }
+
+    let header = header.trim_end();
+    if header.ends_with("=>") {
+        return true;
+    }
+    // A parameter list followed by nothing but modifiers and a return type
+    !keywords_only
+        && header.rfind(')').is_some_and(|index| {
+            header[index + 1..]
+                .chars()
+                .all(|c| c.is_alphanumeric() || c.is_whitespace() || "_$:<>[]?.&*|-".contains(c))
+        })
+}
+
+/// Returns the position of the brace closing the one at `open`
+fn matching_brace(bytes: &[u8], open: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (pos, &byte) in bytes.iter().enumerate().skip(open) {
+        match byte {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Replaces Python function bodies with `...`, keeping docstrings
+fn outline_python(content: &str, builder: &mut Builder<'_>) -> Option<()> {
+    let masked = comments::mask(content, "python")?;
+    let literal_lines = comments::lex(content, "python", false)?.literal_lines;
+    let starts = &builder.line_starts;
+    let line = |index: usize| -> (usize, usize) {
+        let end = starts.get(index + 1).copied().unwrap_or(content.len());
+        (starts[index], end)
+    };
+    let count = starts.len();
+    let is_blank = |index: usize| {
+        let (start, end) = line(index);
+        masked[start..end].trim().is_empty()
+    };
+    let indent = |index: usize| {
+        let (start, end) = line(index);
+        masked[start..end].len() - masked[start..end].trim_start().len()
+    };
+
+    let mut pieces: Vec<Piece> = Vec::new();
+    let mut index = 0;
+    while index < count {
+        let (start, end) = line(index);
+        let code = masked[start..end].trim_start();
+        let is_def =
+            !literal_lines[index] && (code.starts_with("def ") || code.starts_with("async def "));
+        let signature_end = if is_def {
+            signature_end(&masked, start)
+        } else {
+            None
+        };
+        let Some(colon) = signature_end else {
+            index += 1;
+            continue;
+        };
+
+        let mut last = index;
+        while line(last).1 <= colon {
+            last += 1;
+        }
+        let (_, last_end) = line(last);
+        // One-line functions stay as they are
+        if !masked[colon + 1..last_end].trim().is_empty() {
+            index = last + 1;
+            continue;
+        }
+
+        let def_indent = indent(index);
+        let mut body_end = last + 1;
+        while body_end < count
+            && (literal_lines[body_end] || is_blank(body_end) || indent(body_end) > def_indent)
+        {
+            body_end += 1;
+        }
+        while body_end > last + 1 && is_blank(body_end - 1) {
+            body_end -= 1;
+        }
+
+        let mut first = last + 1;
+        while first < body_end && is_blank(first) {
+            first += 1;
+        }
+        // Keep the docstring and everything up to it
+        if first < body_end && is_docstring(&content[line(first).0..]) {
+            first += 1;
+            while first < body_end && literal_lines[first] {
+                first += 1;
+            }
+        }
+        let rest_is_code = (first..body_end).any(|index| !is_blank(index));
+        if rest_is_code {
+            let body_indent = indent(
+                (last + 1..body_end)
+                    .find(|&index| !is_blank(index))
+                    .unwrap_or(first),
+            );
+            pieces.push(Piece {
+                from: line(first).0,
+                to: line(body_end - 1).1,
+                indent: body_indent,
+            });
+        }
+        index = body_end;
+    }
+
+    let mut copied = 0;
+    for piece in pieces {
+        builder.copy(copied, piece.from);
+        let text = format!("{}...\n", " ".repeat(piece.indent));
+        builder.insert(&text, piece.from);
+        copied = piece.to;
+    }
+    builder.copy(copied, content.len());
+    Some(())
+}
+
+/// A run of Python body lines replaced with `...`
+struct Piece {
+    from: usize,
+    to: usize,
+    indent: usize,
+}
+
+/// Returns the position of the colon ending the `def` that starts at `start`
+fn signature_end(masked: &str, start: usize) -> Option<usize> {
+    let mut nesting = 0usize;
+    for (pos, byte) in masked.bytes().enumerate().skip(start) {
+        match byte {
+            b'(' | b'[' | b'{' => nesting += 1,
+            b')' | b']' | b'}' => nesting = nesting.saturating_sub(1),
+            b':' if nesting == 0 => return Some(pos),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns whether `line` starts with a string literal
+fn is_docstring(line: &str) -> bool {
+    let code = line
+        .trim_start()
+        .trim_start_matches(['r', 'R', 'u', 'U', 'b', 'B', 'f', 'F']);
+    code.starts_with('"') || code.starts_with('\'')
+}
+
+/// Builds the outline from pieces of the original text
+struct Builder<'a> {
+    content: &'a str,
+    /// Byte offset of every line start in `content`
+    line_starts: Vec<usize>,
+    text: String,
+    line_numbers: Vec<usize>,
+    at_line_start: bool,
+}
+
+impl<'a> Builder<'a> {
+    fn new(content: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(index, _)| index + 1))
+            .filter(|&start| start < content.len() || start == 0)
+            .collect();
+        Self {
+            content,
+            line_starts,
+            text: String::with_capacity(content.len()),
+            line_numbers: Vec::new(),
+            at_line_start: true,
+        }
+    }
+
+    /// Copies `content[start..end]`
+    fn copy(&mut self, start: usize, end: usize) {
+        let mut pos = start;
+        for piece in self.content[start..end].split_inclusive('\n') {
+            self.push(piece, pos);
+            pos += piece.len();
+        }
+    }
+
+    /// Adds `text` in place of the original text at `offset`
+    fn insert(&mut self, text: &str, offset: usize) {
+        for piece in text.split_inclusive('\n') {
+            self.push(piece, offset);
+        }
+    }
+
+    fn push(&mut self, piece: &str, offset: usize) {
+        if self.at_line_start {
+            let line = self.line_starts.partition_point(|&start| start <= offset);
+            self.line_numbers.push(line.max(1));
+        }
+        self.text.push_str(piece);
+        self.at_line_start = piece.ends_with('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outline_collapses_function_bodies() {
+        let content = "\
+/// A point
+#[derive(Debug)]
+pub struct Point { x: [u8; 2] }
+
+impl Point {
+    /// Creates a point
+    pub fn new(x: [u8; 2]) -> Self {
+        let s = \"fn x() {\";
+        Self { x }
+    }
+
+    fn empty() {}
+}
+";
+        let outlined = outline(content, "rust").unwrap();
+        assert_eq!(
+            outlined.text,
+            "\
+/// A point
+#[derive(Debug)]
+pub struct Point { x: [u8; 2] }
+
+impl Point {
+    /// Creates a point
+    pub fn new(x: [u8; 2]) -> Self { ... }
+
+    fn empty() {}
+}
+"
+        );
+        assert_eq!(outlined.line_numbers, vec![1, 2, 3, 4, 5, 6, 7, 11, 12, 13]);
+
+        let java = "class A {\n    int f(int x) throws E {\n        return x;\n    }\n    void g() {\n        if (x) { y(); }\n    }\n}\n";
+        assert_eq!(
+            outline(java, "java").unwrap().text,
+            "class A {\n    int f(int x) throws E { ... }\n    void g() { ... }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_outline_python_keeps_docstrings() {
+        let content = "\
+class A:
+    def f(self, x: int) -> dict[str, int]:
+        \"\"\"Docs
+
+        more\"\"\"
+        y = x
+        return {}
+
+    def g(self): return 1
+
+    async def h(
+        self,
+    ):
+        pass
+";
+        let outlined = outline(content, "python").unwrap();
+        assert_eq!(
+            outlined.text,
+            "\
+class A:
+    def f(self, x: int) -> dict[str, int]:
+        \"\"\"Docs
+
+        more\"\"\"
+        ...
+
+    def g(self): return 1
+
+    async def h(
+        self,
+    ):
+        ...
+"
+        );
+        assert_eq!(
+            outlined.line_numbers,
+            vec![1, 2, 3, 4, 5, 6, 8, 9, 10, 11, 12, 13, 14]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Path queries for bulk selection
+//!
+//! A query is a list of terms separated by whitespace, matched against
+//! root-relative paths written with `/`:
+//! - a glob containing `/` matches the whole path (`api/**/*.proto`)
+//! - a glob without `/` matches the file name (`*.rs`)
+//! - `re:` starts a regular expression searched in the path (`re:_test\.go$`)
+//! - a leading `!` turns either into an exclusion (`!**/tests/**`)
+//!
+//! A file matches when it matches any include term, or there are none, and
+//! no exclude term.
+
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+use std::path::Path;
+
+/// One compiled term of a query
+#[derive(Debug, Clone)]
+enum Term {
+    /// Glob pattern
+    Glob(Pattern),
+    /// Regular expression
+    Regex(Regex),
+}
+
+impl Term {
+    fn parse(source: &str) -> Result<Self, String> {
+        source.strip_prefix("re:").map_or_else(
+            || {
+                Pattern::new(source)
+                    .map(Self::Glob)
+                    .map_err(|e| format!("Invalid glob {source:?}: {e}"))
+            },
+            |pattern| {
+                Regex::new(pattern)
+                    .map(Self::Regex)
+                    .map_err(|e| format!("Invalid regex {pattern:?}: {e}"))
+            },
+        )
+    }
+
+    fn matches(&self, relative: &Path, path: &str) -> bool {
+        match self {
+            Self::Glob(pattern) => glob_matches(pattern, relative),
+            Self::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+/// Compiled selection query
+#[derive(Debug, Clone)]
+pub struct PathQuery {
+    /// Terms a file must match one of
+    include: Vec<Term>,
+    /// Terms a file must match none of
+    exclude: Vec<Term>,
+}
+
+impl PathQuery {
+    /// Compiles a query
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query has no terms or a term is not a valid
+    /// glob or regular expression
+    pub fn parse(query: &str) -> Result<Self, String> {
+        let mut include = Vec::new();
+        let mut exclude = Vec::new();
+        for source in query.split_whitespace() {
+            match source.strip_prefix('!') {
+                Some(source) => exclude.push(Term::parse(source)?),
+                None => include.push(Term::parse(source)?),
+            }
+        }
+        if include.is_empty() && exclude.is_empty() {
+            return Err("Empty query".to_string());
+        }
+        Ok(Self { include, exclude })
+    }
+
+    /// Returns whether the root-relative path `relative` matches
+    #[must_use]
+    pub fn matches(&self, relative: &Path) -> bool {
+        let path = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|term| term.matches(relative, &path)))
+            && !self
+                .exclude
+                .iter()
+                .any(|term| term.matches(relative, &path))
+    }
+}
+
+/// Matches a glob against a root-relative path, or just its file name if the glob has no `/`
+#[must_use]
+pub fn glob_matches(pattern: &Pattern, relative: &Path) -> bool {
+    let options = MatchOptions {
+        require_literal_separator: true,
+        ..MatchOptions::new()
+    };
+
+    if pattern.as_str().contains('/') {
+        pattern.matches_path_with(relative, options)
+    } else {
+        relative
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| pattern.matches_with(name, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(query: &str, path: &str) -> bool {
+        PathQuery::parse(query).unwrap().matches(Path::new(path))
+    }
+
+    #[test]
+    fn test_path_query() {
+        let protos = "api/**/*.proto !**/tests/**";
+        assert!(matches(protos, "api/user.proto"));
+        assert!(matches(protos, "api/v1/billing/invoice.proto"));
+        assert!(!matches(protos, "api/tests/fixture.proto"));
+        assert!(!matches(protos, "api/v1/tests/nested/fixture.proto"));
+        assert!(!matches(protos, "web/api/user.proto"));
+        assert!(!matches(protos, "api/user.rs"));
+
+        // Globs without a slash match the name, several includes are alternatives
+        assert!(matches("*.rs *.toml", "src/deep/lib.rs"));
+        assert!(matches("*.rs *.toml", "Cargo.toml"));
+        assert!(!matches("*.rs *.toml", "README.md"));
+
+        // Regexes search the whole path
+        assert!(matches(r"re:_test\.go$", "pkg/auth/login_test.go"));
+        assert!(matches(r"re:^src/ !re:mock", "src/db.rs"));
+        assert!(!matches(r"re:^src/ !re:mock", "src/mocks/db.rs"));
+
+        // Only exclusions: everything else matches
+        assert!(matches("!*.lock", "src/main.rs"));
+        assert!(!matches("!*.lock", "Cargo.lock"));
+
+        assert!(PathQuery::parse("  ").is_err());
+        assert!(PathQuery::parse("re:(").is_err());
+        assert!(PathQuery::parse("src/[").is_err());
+    }
+}
@@ -0,0 +1,262 @@
+//! Module references in Rust source
+//!
+//! Finds the `mod name;` declarations of a file and the `crate::` paths it
+//! names, both in `use` trees and inline in code. Comments and literals are
+//! masked first, so commented-out imports are not followed. Paths through
+//! `self::` and `super::` are not collected: inline modules such as
+//! `mod tests` make them ambiguous without a full parse. The names of the
+//! items a file declares are found the same way, so a path such as
+//! `crate::Error::new` can be traced to the crate root. Resolving the
+//! references to files is done by `utils::rust_deps`.
+
+use crate::core::comments;
+use regex::Regex;
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+
+/// `mod name;` declarations, with or without visibility and attributes
+static MOD_DECL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bmod\s+(?:r#)?([A-Za-z_][A-Za-z0-9_]*)\s*;").expect("valid regex")
+});
+
+/// `use` declarations up to their semicolon
+static USE_DECL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\buse\s+([^;]+);").expect("valid regex"));
+
+/// Qualified `crate::` paths written inline in code
+static CRATE_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bcrate((?:\s*::\s*[A-Za-z_][A-Za-z0-9_]*)+)").expect("valid regex")
+});
+
+/// Item declarations other than constants and statics
+static ITEM_DECL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\b(?:struct|enum|union|trait|type|fn|macro_rules\s*!)\s+(?:r#)?([A-Za-z_][A-Za-z0-9_]*)",
+    )
+    .expect("valid regex")
+});
+
+/// `const` and `static` items, told apart from `const fn` by their type
+static VALUE_DECL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:const|static(?:\s+mut)?)\s+(?:r#)?([A-Za-z_][A-Za-z0-9_]*)\s*:")
+        .expect("valid regex")
+});
+
+/// Inline `mod name { ... }` blocks
+static INLINE_MOD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bmod\s+(?:r#)?([A-Za-z_][A-Za-z0-9_]*)\s*\{").expect("valid regex")
+});
+
+/// A reference from a Rust file to a module of its crate
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Reference {
+    /// A `mod name;` declaration, whose file sits next to the declaring module
+    Module(String),
+    /// A path below `crate::`, without the leading `crate`
+    CratePath(Vec<String>),
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Module(name) => write!(f, "mod {name}"),
+            Self::CratePath(segments) => write!(f, "crate::{}", segments.join("::")),
+        }
+    }
+}
+
+/// Returns the module references of a Rust file, sorted and without duplicates
+#[must_use]
+pub fn references(content: &str) -> Vec<Reference> {
+    let masked = comments::mask(content, "rust").unwrap_or_else(|| content.to_string());
+    let mut found = BTreeSet::new();
+
+    for captures in MOD_DECL.captures_iter(&masked) {
+        found.insert(Reference::Module(captures[1].to_string()));
+    }
+    for captures in USE_DECL.captures_iter(&masked) {
+        for mut path in expand_use_tree(&captures[1]) {
+            if path.first().is_some_and(|first| first == "crate") && path.len() > 1 {
+                path.remove(0);
+                found.insert(Reference::CratePath(path));
+            }
+        }
+    }
+    for captures in CRATE_PATH.captures_iter(&masked) {
+        let segments = captures[1]
+            .split("::")
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_string)
+            .collect();
+        found.insert(Reference::CratePath(segments));
+    }
+
+    found.into_iter().collect()
+}
+
+/// Returns the names a Rust file declares or imports, other than `mod name;` modules
+///
+/// Items nested in functions or inline modules are included too, which is
+/// close enough to tell an item of the file from a module that is missing.
+#[must_use]
+pub fn items(content: &str) -> BTreeSet<String> {
+    let masked = comments::mask(content, "rust").unwrap_or_else(|| content.to_string());
+    let mut found = BTreeSet::new();
+
+    for regex in [&*ITEM_DECL, &*VALUE_DECL, &*INLINE_MOD] {
+        for captures in regex.captures_iter(&masked) {
+            found.insert(captures[1].to_string());
+        }
+    }
+    for captures in USE_DECL.captures_iter(&masked) {
+        found.extend(
+            expand_use_tree(&captures[1])
+                .into_iter()
+                .filter_map(|mut path| path.pop()),
+        );
+    }
+
+    found
+}
+
+/// Expands a use tree such as `crate::{a::B, c::{self, D}}` into its paths
+///
+/// Aliases are dropped, a trailing `self` names the module before it and a
+/// glob leaves the module path.
+fn expand_use_tree(tree: &str) -> Vec<Vec<String>> {
+    let tree = tree.trim().trim_start_matches("::").trim();
+    let Some(open) = tree.find('{') else {
+        let path = tree.split(" as ").next().unwrap_or(tree);
+        let mut segments: Vec<String> = path
+            .split("::")
+            .map(str::trim)
+            .filter(|segment| !segment.is_empty() && *segment != "*")
+            .map(str::to_string)
+            .collect();
+        if segments.len() > 1 && segments.last().is_some_and(|last| last == "self") {
+            segments.pop();
+        }
+        return vec![segments];
+    };
+
+    let prefix: Vec<String> = tree[..open]
+        .split("::")
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_string)
+        .collect();
+    let inner = tree[open + 1..].trim_end();
+    let inner = inner.strip_suffix('}').unwrap_or(inner);
+
+    let mut paths = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut items = Vec::new();
+    for (index, ch) in inner.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(&inner[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&inner[start..]);
+
+    for item in items.into_iter().filter(|item| !item.trim().is_empty()) {
+        for rest in expand_use_tree(item) {
+            let mut path = prefix.clone();
+            if rest != ["self"] {
+                path.extend(rest);
+            }
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(segments: &[&str]) -> Reference {
+        Reference::CratePath(segments.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn test_references() {
+        let content = r#"
+pub mod tree;
+#[cfg(test)]
+mod tests;
+mod inline {
+    fn f() {}
+}
+
+use crate::core::types::{CanonicalPath, SelectionState};
+use crate::ui::{
+    self,
+    icons::IconManager as Icons,
+    theme::*,
+};
+use std::path::Path;
+use super::sibling;
+// use crate::commented::Out;
+
+fn build() {
+    let text = "crate::in_string::Nope";
+    crate::utils::perf::record(text);
+}
+"#;
+        assert_eq!(
+            references(content),
+            vec![
+                Reference::Module("tests".to_string()),
+                Reference::Module("tree".to_string()),
+                path(&["core", "types"]),
+                path(&["core", "types", "CanonicalPath"]),
+                path(&["core", "types", "SelectionState"]),
+                path(&["ui"]),
+                path(&["ui", "icons", "IconManager"]),
+                path(&["ui", "theme"]),
+                path(&["utils", "perf", "record"]),
+            ]
+        );
+        assert_eq!(path(&["ui", "icons"]).to_string(), "crate::ui::icons");
+        assert_eq!(Reference::Module("a".to_string()).to_string(), "mod a");
+    }
+
+    #[test]
+    fn test_items() {
+        let content = r#"
+pub mod cache;
+pub mod inline {}
+pub use crate::core::types::{AppState, CanonicalPath};
+pub type Result<T> = std::result::Result<T, Error>;
+pub struct Error;
+pub const fn limit() -> usize { MAX }
+pub const MAX: usize = 10;
+static mut COUNT: u32 = 0;
+macro_rules! log { () => {} }
+// pub struct Commented;
+"#;
+        let found: Vec<String> = items(content).into_iter().collect();
+        assert_eq!(
+            found,
+            [
+                "AppState",
+                "COUNT",
+                "CanonicalPath",
+                "Error",
+                "MAX",
+                "Result",
+                "inline",
+                "limit",
+                "log"
+            ]
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Per-file size cap
+//!
+//! A single oversized file (a log, a minified bundle) should not blow up the
+//! whole export. Files over the cap are either skipped or cut down to their
+//! first and last lines with a marker in between.
+
+use crate::core::split::SplitUnit;
+use crate::core::types::{FileReadStrategy, FileSize};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::ops::Range;
+
+/// What happens to a file over the cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizeAction {
+    /// Keep the first and last lines
+    #[default]
+    Truncate,
+    /// Leave the file out
+    Skip,
+}
+
+impl OversizeAction {
+    /// All actions, in display order
+    pub const ALL: [Self; 2] = [Self::Truncate, Self::Skip];
+
+    /// Name used in the config file and on the command line
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Truncate => "truncate",
+            Self::Skip => "skip",
+        }
+    }
+
+    /// Parses a config name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Human-readable label for the settings UI
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Truncate => "Keep first and last lines",
+            Self::Skip => "Skip the file",
+        }
+    }
+}
+
+/// Per-file size cap settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSizeCap {
+    /// Whether the cap is enforced
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum size of a single file
+    pub max_size: usize,
+    /// Unit `max_size` is measured in
+    #[serde(default)]
+    pub unit: SplitUnit,
+    /// What happens to files over the cap
+    #[serde(default)]
+    pub action: OversizeAction,
+    /// Lines kept from the start of a truncated file
+    pub head_lines: usize,
+    /// Lines kept from the end of a truncated file
+    pub tail_lines: usize,
+}
+
+impl Default for FileSizeCap {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size: 20_000,
+            unit: SplitUnit::default(),
+            action: OversizeAction::default(),
+            head_lines: 200,
+            tail_lines: 50,
+        }
+    }
+}
+
+impl FileSizeCap {
+    /// Whether a file of `size` bytes is skipped before its contents are read
+    ///
+    /// Only byte caps with the skip action can be decided from the size alone.
+    #[must_use]
+    pub fn skips_without_reading(&self, size: FileSize) -> bool {
+        self.enabled
+            && self.unit == SplitUnit::Bytes
+            && self.action == OversizeAction::Skip
+            && size.bytes() > self.max_size as u64
+    }
+
+    /// Whether only the first and last lines of a file of `size` bytes are read
+    ///
+    /// Large files over a byte cap in truncate mode are memory-mapped and
+    /// only the lines kept are decoded, see [`head_tail_ranges`].
+    #[must_use]
+    pub fn truncates_without_reading(&self, size: FileSize) -> bool {
+        self.enabled
+            && self.unit == SplitUnit::Bytes
+            && self.action == OversizeAction::Truncate
+            && size.bytes() > self.max_size as u64
+            && matches!(size.read_strategy(), FileReadStrategy::MemoryMapped)
+    }
+}
+
+/// How a file was cut down to fit the cap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Truncation {
+    /// Size of the original file in bytes
+    pub original_size: u64,
+    /// Number of lines in the original file
+    pub original_lines: usize,
+    /// Lines kept before the marker
+    pub head_lines: usize,
+    /// Lines replaced by the marker
+    pub removed_lines: usize,
+}
+
+/// Keeps the first `head` and last `tail` lines of `content`
+///
+/// The removed lines are replaced by a single `[... truncated N lines ...]`
+/// marker line. Returns `None` if the file has too few lines to cut.
+#[must_use]
+pub fn truncate_head_tail(content: &str, head: usize, tail: usize) -> Option<(String, Truncation)> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    if lines.len() <= head + tail {
+        return None;
+    }
+
+    let mut kept = lines[..head].concat();
+    kept.push_str(&lines[lines.len() - tail..].concat());
+    Some(join_head_tail(
+        &kept,
+        head,
+        lines.len(),
+        content.len() as u64,
+    ))
+}
+
+/// Byte ranges of the lines a truncation keeps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadTailRanges {
+    /// The first lines
+    pub head: Range<usize>,
+    /// The last lines
+    pub tail: Range<usize>,
+    /// Number of lines in the whole file
+    pub lines: usize,
+}
+
+/// Finds the first `head` and last `tail` lines of undecoded file contents
+///
+/// Lines end after `\n`, as in [`truncate_head_tail`]. Returns `None` if the
+/// file has too few lines to cut.
+#[must_use]
+pub fn head_tail_ranges(bytes: &[u8], head: usize, tail: usize) -> Option<HeadTailRanges> {
+    let newline_ends = || {
+        bytes
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte == b'\n')
+            .map(|(index, _)| index + 1)
+    };
+    let newlines = newline_ends().count();
+    let lines = newlines + usize::from(bytes.last().is_some_and(|byte| *byte != b'\n'));
+    if lines <= head + tail {
+        return None;
+    }
+
+    // Line `n` starts after the `n`th newline
+    let head_end = head
+        .checked_sub(1)
+        .map_or(0, |n| newline_ends().nth(n).unwrap_or(bytes.len()));
+    // Without a trailing newline and with no tail, no newline starts the tail
+    let tail_start = newlines
+        .checked_sub(lines - tail)
+        .and_then(|n| newline_ends().rev().nth(n))
+        .unwrap_or(bytes.len());
+    Some(HeadTailRanges {
+        head: 0..head_end,
+        tail: tail_start..bytes.len(),
+        lines,
+    })
+}
+
+/// Inserts the truncation marker into the kept lines of a file
+///
+/// `kept` holds the first `head` lines followed by the last lines of a file
+/// of `lines` lines and `original_size` bytes.
+#[must_use]
+pub fn join_head_tail(
+    kept: &str,
+    head: usize,
+    lines: usize,
+    original_size: u64,
+) -> (String, Truncation) {
+    let removed = lines - kept.split_inclusive('\n').count();
+    (
+        insert_marker(kept, head, removed),
+        Truncation {
+            original_size,
+            original_lines: lines,
+            head_lines: head,
+            removed_lines: removed,
+        },
+    )
+}
+
+/// Inserts the marker for `removed` lines before the line at index `at` of `text`
+#[must_use]
+pub fn insert_marker(text: &str, at: usize, removed: usize) -> String {
+    let head_len = text.split_inclusive('\n').take(at).map(str::len).sum();
+    let (head_text, tail_text) = text.split_at(head_len);
+
+    let mut marked = head_text.to_string();
+    let _ = writeln!(
+        marked,
+        "[... truncated {} lines ...]",
+        format_count(removed)
+    );
+    marked.push_str(tail_text);
+    marked
+}
+
+/// Formats a count with thousands separators, e.g. `12,345`
+#[must_use]
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_head_tail() {
+        let content: String = (1..=10).map(|n| format!("line {n}\n")).collect();
+
+        let (truncated, truncation) = truncate_head_tail(&content, 2, 3).unwrap();
+        assert_eq!(
+            truncated,
+            "line 1\nline 2\n[... truncated 5 lines ...]\nline 8\nline 9\nline 10\n"
+        );
+        assert_eq!(truncation.original_lines, 10);
+        assert_eq!(truncation.head_lines, 2);
+        assert_eq!(truncation.removed_lines, 5);
+        assert_eq!(truncation.original_size, content.len() as u64);
+
+        assert!(truncate_head_tail(&content, 5, 5).is_none());
+    }
+
+    #[test]
+    fn test_head_tail_ranges() {
+        let content: String = (1..=10).map(|n| format!("line {n}\n")).collect();
+        let ranges = head_tail_ranges(content.as_bytes(), 2, 3).unwrap();
+        assert_eq!(&content[ranges.head.clone()], "line 1\nline 2\n");
+        assert_eq!(&content[ranges.tail.clone()], "line 8\nline 9\nline 10\n");
+        assert_eq!(ranges.lines, 10);
+
+        // Joining the ranges gives the same result as truncating the whole file
+        let kept = format!("{}{}", &content[ranges.head], &content[ranges.tail]);
+        assert_eq!(
+            Some(join_head_tail(&kept, 2, ranges.lines, content.len() as u64)),
+            truncate_head_tail(&content, 2, 3)
+        );
+
+        // A last line without a newline counts too
+        let ranges = head_tail_ranges(b"a\nb\nc", 0, 1).unwrap();
+        assert_eq!((ranges.head, ranges.tail, ranges.lines), (0..0, 4..5, 3));
+        assert!(head_tail_ranges(b"a\nb\n", 1, 1).is_none());
+
+        // No tail keeps nothing from the end, with or without a trailing newline
+        let ranges = head_tail_ranges(b"a\nb\nc", 1, 0).unwrap();
+        assert_eq!((ranges.head, ranges.tail, ranges.lines), (0..2, 5..5, 3));
+        let ranges = head_tail_ranges(b"a\nb\nc\n", 1, 0).unwrap();
+        assert_eq!((ranges.head, ranges.tail, ranges.lines), (0..2, 6..6, 3));
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(12_345), "12,345");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+}
@@ -0,0 +1,257 @@
+//! Splitting large exports into numbered parts
+//!
+//! Files are packed into parts in selection order, each part staying under the
+//! configured limit. A file is only cut into line ranges when it does not fit
+//! into a part on its own. The limit covers the whole part: the room left for
+//! files is what the part's envelope, tree and manifest leave over.
+
+use serde::{Deserialize, Serialize};
+
+/// Unit a size limit is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitUnit {
+    /// Tokens, as counted by the configured tokenizer
+    #[default]
+    Tokens,
+    /// UTF-8 bytes
+    Bytes,
+}
+
+impl SplitUnit {
+    /// All units, in display order
+    pub const ALL: [Self; 2] = [Self::Tokens, Self::Bytes];
+
+    /// Name used in the config file and on the command line
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Tokens => "tokens",
+            Self::Bytes => "bytes",
+        }
+    }
+
+    /// Parses a config name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|unit| unit.name() == name)
+    }
+}
+
+/// Settings for splitting output into parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SplitLimit {
+    /// Whether output is split
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum size of one part
+    pub limit: usize,
+    /// Unit `limit` is measured in
+    #[serde(default)]
+    pub unit: SplitUnit,
+}
+
+impl Default for SplitLimit {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            limit: 100_000,
+            unit: SplitUnit::default(),
+        }
+    }
+}
+
+/// A file, or a line range of a file, placed in a part
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartEntry {
+    /// Index of the file in the input
+    pub file: usize,
+    /// 1-based inclusive line range when the file was cut
+    pub lines: Option<(usize, usize)>,
+}
+
+/// Manifest line describing where a file went
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestEntry {
+    /// Root-relative path
+    pub path: String,
+    /// 1-based inclusive line range when the file was cut
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<(usize, usize)>,
+}
+
+impl std::fmt::Display for ManifestEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.lines {
+            Some((start, end)) => write!(f, "{} (lines {start}-{end})", self.path),
+            None => f.write_str(&self.path),
+        }
+    }
+}
+
+/// Assigns files to parts so that each part stays within its room
+///
+/// `contents` are the file contents in output order, `headers` the size each
+/// file adds to a part besides its contents, and `room` the size available
+/// for files in the 0-based part. `measure` returns the size of a piece of
+/// text in the limit's unit. Files that exceed the room of a part on their
+/// own are cut at line boundaries. Always returns at least one part.
+pub fn plan_parts(
+    contents: &[&str],
+    headers: &[usize],
+    room: impl Fn(usize) -> usize,
+    measure: impl Fn(&str) -> usize,
+) -> Vec<Vec<PartEntry>> {
+    let mut parts: Vec<Vec<PartEntry>> = vec![Vec::new()];
+    let mut used = 0;
+
+    let mut place = |entry: PartEntry, cost: usize, parts: &mut Vec<Vec<PartEntry>>| {
+        if used + cost > room(parts.len() - 1) && parts.last().is_some_and(|part| !part.is_empty())
+        {
+            parts.push(Vec::new());
+            used = 0;
+        }
+        used += cost;
+        if let Some(part) = parts.last_mut() {
+            part.push(entry);
+        }
+    };
+
+    for (file, content) in contents.iter().enumerate() {
+        let header = headers.get(file).copied().unwrap_or_default();
+        let cost = measure(content) + header;
+        // Room of a part that starts with this file
+        let limit = room(parts.len());
+        if cost <= limit {
+            place(PartEntry { file, lines: None }, cost, &mut parts);
+            continue;
+        }
+
+        for (start, end, cost) in line_chunks(content, limit.saturating_sub(header), &measure) {
+            place(
+                PartEntry {
+                    file,
+                    lines: Some((start, end)),
+                },
+                cost + header,
+                &mut parts,
+            );
+        }
+    }
+
+    parts
+}
+
+/// Cuts `content` into runs of whole lines that each stay under `limit`
+///
+/// Returns 1-based inclusive line ranges with their cost. A single line longer
+/// than the limit forms a chunk of its own.
+fn line_chunks(
+    content: &str,
+    limit: usize,
+    measure: &impl Fn(&str) -> usize,
+) -> Vec<(usize, usize, usize)> {
+    let mut chunks = Vec::new();
+    let mut start = 1;
+    let mut cost = 0;
+
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        let line_cost = measure(line);
+        if cost > 0 && cost + line_cost > limit {
+            chunks.push((start, line_number - 1, cost));
+            start = line_number;
+            cost = 0;
+        }
+        cost += line_cost;
+    }
+
+    let line_count = content.split_inclusive('\n').count();
+    if line_count >= start {
+        chunks.push((start, line_count, cost));
+    }
+    chunks
+}
+
+/// Returns the given 1-based inclusive line range of `content`
+#[must_use]
+pub fn slice_lines(content: &str, (start, end): (usize, usize)) -> String {
+    content
+        .split_inclusive('\n')
+        .skip(start - 1)
+        .take(end + 1 - start)
+        .collect()
+}
+
+/// Inserts `.partN` before the extension of `file_name`
+#[must_use]
+pub fn part_file_name(file_name: &str, part: usize) -> String {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => format!("{stem}.part{part}.{extension}"),
+        _ => format!("{file_name}.part{part}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(parts: &[Vec<PartEntry>]) -> Vec<Vec<(usize, Option<(usize, usize)>)>> {
+        parts
+            .iter()
+            .map(|part| part.iter().map(|entry| (entry.file, entry.lines)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_files_are_kept_whole() {
+        let contents = ["aaaa", "bbbbbb", "cc", "dddddddd"];
+        let parts = plan_parts(&contents, &[0; 4], |_| 10, str::len);
+        assert_eq!(
+            lines(&parts),
+            vec![vec![(0, None), (1, None)], vec![(2, None), (3, None)]]
+        );
+    }
+
+    #[test]
+    fn test_oversized_file_is_cut_by_lines() {
+        let big = "111\n222\n333\n444\n";
+        let contents = ["xx", big, "y"];
+        let parts = plan_parts(&contents, &[0; 3], |_| 9, str::len);
+        assert_eq!(
+            lines(&parts),
+            vec![
+                vec![(0, None)],
+                vec![(1, Some((1, 2)))],
+                vec![(1, Some((3, 4))), (2, None)],
+            ]
+        );
+        assert_eq!(slice_lines(big, (3, 4)), "333\n444\n");
+        assert!(plan_parts(&[], &[], |_| 10, str::len)[0].is_empty());
+    }
+
+    #[test]
+    fn test_headers_and_room_per_part() {
+        // The first part has less room, e.g. for the directory tree
+        let contents = ["aaaa", "bbbb", "cccc"];
+        let parts = plan_parts(
+            &contents,
+            &[2; 3],
+            |part| if part == 0 { 6 } else { 12 },
+            str::len,
+        );
+        assert_eq!(
+            lines(&parts),
+            vec![vec![(0, None)], vec![(1, None), (2, None)]]
+        );
+    }
+
+    #[test]
+    fn test_part_file_name() {
+        assert_eq!(
+            part_file_name("codebase_export.md", 1),
+            "codebase_export.part1.md"
+        );
+        assert_eq!(part_file_name("export", 2), "export.part2");
+    }
+}
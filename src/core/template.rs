@@ -0,0 +1,189 @@
+//! User-defined output templates
+//!
+//! Templates are TOML files in the `templates` directory next to `config.json`.
+//! Each one defines a header, a per-file block, an optional tree block and a
+//! footer. Blocks contain `{placeholder}` fields that are filled in during
+//! generation; `{{` and `}}` produce literal braces.
+
+use crate::core::types::OutputFormat;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A template-driven output layout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputTemplate {
+    /// Template name, taken from the file stem
+    #[serde(skip)]
+    pub name: String,
+    /// File extension used when saving output
+    #[serde(default = "default_extension")]
+    pub extension: String,
+    /// Emitted once before everything else (`{root}`, `{file_count}`, `{budget}`,
+    /// `{part}`, `{part_count}`, `{manifest}`, `{git}`)
+    #[serde(default)]
+    pub header: String,
+    /// Emitted when the directory tree is enabled (`{tree}`)
+    #[serde(default)]
+    pub tree: String,
+    /// Emitted once per file (`{index}`, `{path}`, `{lang}`, `{content}`, `{size}`, `{tokens}`)
+    pub file: String,
+    /// Emitted once after everything else (same placeholders as `header`)
+    #[serde(default)]
+    pub footer: String,
+}
+
+fn default_extension() -> String {
+    "txt".to_string()
+}
+
+impl OutputTemplate {
+    /// Parses a template from TOML source
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TOML is invalid or the `file` block is missing
+    pub fn parse(name: &str, source: &str) -> Result<Self, String> {
+        let mut template: Self =
+            toml::from_str(source).map_err(|e| format!("Invalid template {name}: {e}"))?;
+        template.name = name.to_string();
+        Ok(template)
+    }
+
+    /// Renders a block, replacing `{name}` placeholders with values from `lookup`
+    ///
+    /// Placeholders that `lookup` does not know are kept verbatim. Values are
+    /// inserted as-is and never re-scanned, so file contents containing braces
+    /// are safe.
+    pub fn render<'a>(block: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> String {
+        let mut output = String::with_capacity(block.len());
+        let mut rest = block;
+
+        while let Some(pos) = rest.find(['{', '}']) {
+            output.push_str(&rest[..pos]);
+            let tail = &rest[pos..];
+
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                output.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            }
+
+            if let Some(inner) = tail.strip_prefix('{') {
+                if let Some(end) = inner.find('}') {
+                    if let Some(value) = lookup(&inner[..end]) {
+                        output.push_str(value);
+                        rest = &inner[end + 1..];
+                        continue;
+                    }
+                }
+            }
+
+            output.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+
+        output.push_str(rest);
+        output
+    }
+}
+
+/// Loads all `*.toml` templates from a directory, sorted by name
+///
+/// Returns the templates that parsed successfully together with an error
+/// message for each file that did not. Templates named like a built-in
+/// format would be unreachable, so they are skipped with an error too. A
+/// missing directory yields no templates.
+pub fn load_templates(dir: &Path) -> (Vec<OutputTemplate>, Vec<String>) {
+    let mut templates = Vec::new();
+    let mut errors = Vec::new();
+
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return (templates, errors);
+    };
+
+    for path in entries.filter_map(Result::ok).map(|e| e.path()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if OutputFormat::is_builtin_name(name) {
+            errors.push(format!(
+                "Template {name} is skipped: its name is taken by a built-in format, rename the file"
+            ));
+            continue;
+        }
+
+        match std::fs::read_to_string(&path) {
+            Ok(source) => match OutputTemplate::parse(name, &source) {
+                Ok(template) => templates.push(template),
+                Err(e) => errors.push(e),
+            },
+            Err(e) => errors.push(format!("Failed to read template {name}: {e}")),
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    (templates, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        let template = OutputTemplate::parse(
+            "claude",
+            r#"
+extension = "xml"
+header = "<documents>\n"
+file = "<document index=\"{index}\"><source>{path}</source></document>\n"
+footer = "</documents>\n"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(template.name, "claude");
+        assert_eq!(template.extension, "xml");
+        assert!(template.tree.is_empty());
+        assert!(OutputTemplate::parse("broken", "header = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_load_templates_skips_builtin_names() {
+        let dir = tempfile::TempDir::new().unwrap();
+        for name in ["review", "XML", "md"] {
+            std::fs::write(
+                dir.path().join(format!("{name}.toml")),
+                "file = \"{content}\"",
+            )
+            .unwrap();
+        }
+
+        let (templates, errors) = load_templates(dir.path());
+        let names: Vec<&str> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["review"]);
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|e| e.contains("built-in format")));
+    }
+
+    #[test]
+    fn test_render_placeholders() {
+        let lookup = |key: &str| match key {
+            "path" => Some("src/main.rs"),
+            "content" => Some("fn f() { {path} }"),
+            _ => None,
+        };
+
+        assert_eq!(
+            OutputTemplate::render("{path}: {content} {unknown} {{literal}}", lookup),
+            "src/main.rs: fn f() { {path} } {unknown} {literal}"
+        );
+        assert_eq!(
+            OutputTemplate::render("dangling { and }", lookup),
+            "dangling { and }"
+        );
+    }
+}
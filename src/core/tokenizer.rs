@@ -0,0 +1,272 @@
+//! Offline token counting for the supported model families
+//!
+//! The BPE vocabularies are embedded in the binary, so counting never touches
+//! the network. `Estimate` keeps the old chars/4 heuristic for very large
+//! selections where exact counts are not worth the cost.
+
+use crate::core::encoding::{self, EncodingConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Counts tokens in a piece of text
+pub trait Tokenizer: Send + Sync {
+    /// Returns the number of tokens `text` encodes to
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Character-based approximation (roughly 1 token = 4 chars)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CharEstimateTokenizer;
+
+impl Tokenizer for CharEstimateTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+}
+
+/// Byte-pair encoding tokenizer backed by an embedded vocabulary
+pub struct BpeTokenizer(&'static tiktoken_rs::CoreBPE);
+
+impl Tokenizer for BpeTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.0.encode_ordinary(text).len()
+    }
+}
+
+impl std::fmt::Debug for BpeTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("BpeTokenizer")
+            .field(&"<vocabulary>")
+            .finish()
+    }
+}
+
+/// Model family whose tokenizer is used for counts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerModel {
+    /// GPT-4 / GPT-3.5 family (`cl100k_base`)
+    #[default]
+    Cl100k,
+    /// GPT-4o / o-series family (`o200k_base`)
+    O200k,
+    /// Fast chars/4 approximation
+    Estimate,
+}
+
+impl TokenizerModel {
+    /// All selectable models, in display order
+    pub const ALL: [Self; 3] = [Self::Cl100k, Self::O200k, Self::Estimate];
+
+    /// Name used in the config file
+    #[must_use]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Cl100k => "cl100k",
+            Self::O200k => "o200k",
+            Self::Estimate => "estimate",
+        }
+    }
+
+    /// Parses a config name
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|model| model.name() == name)
+    }
+
+    /// Human-readable label for the settings UI
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Cl100k => "GPT-4 / GPT-3.5 (cl100k)",
+            Self::O200k => "GPT-4o / o-series (o200k)",
+            Self::Estimate => "Fast estimate (chars / 4)",
+        }
+    }
+
+    /// Returns the tokenizer for this model
+    ///
+    /// Vocabularies are decoded on first use and shared afterwards.
+    pub fn tokenizer(&self) -> Box<dyn Tokenizer> {
+        match self {
+            Self::Cl100k => Box::new(BpeTokenizer(tiktoken_rs::cl100k_base_singleton())),
+            Self::O200k => Box::new(BpeTokenizer(tiktoken_rs::o200k_base_singleton())),
+            Self::Estimate => Box::new(CharEstimateTokenizer),
+        }
+    }
+
+    /// Counts the tokens in `text`
+    pub fn count_tokens(&self, text: &str) -> usize {
+        self.tokenizer().count_tokens(text)
+    }
+}
+
+/// Cached token count for a file
+#[derive(Debug, Clone, Copy)]
+struct CachedCount {
+    len: u64,
+    modified: Option<SystemTime>,
+    model: TokenizerModel,
+    encoding: EncodingConfig,
+    tokens: usize,
+}
+
+/// Per-file token counts for the current selection, reused until a file changes
+///
+/// The app keeps one cache and counts on a background thread: it sends the
+/// [`Self::subset`] for the selection, and merges the counted subset back
+/// with [`Self::merge`].
+#[derive(Debug, Clone, Default)]
+pub struct FileTokenCache {
+    entries: HashMap<PathBuf, CachedCount>,
+}
+
+impl FileTokenCache {
+    /// Creates an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a cache holding only the entries of `paths`
+    #[must_use]
+    pub fn subset<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> Self {
+        Self {
+            entries: paths
+                .into_iter()
+                .filter_map(|path| Some((path.to_path_buf(), *self.entries.get(path)?)))
+                .collect(),
+        }
+    }
+
+    /// Adds the entries of `other`, replacing those of the same files
+    pub fn merge(&mut self, other: Self) {
+        self.entries.extend(other.entries);
+    }
+
+    /// Returns the total token count of `paths` for `model`
+    ///
+    /// Files are decoded with `encoding`, as the generator reads them. Files
+    /// that cannot be decoded fall back to a bytes/4 estimate.
+    pub fn count_files<'a>(
+        &mut self,
+        paths: impl IntoIterator<Item = &'a Path>,
+        model: TokenizerModel,
+        encoding: EncodingConfig,
+    ) -> usize {
+        use rayon::prelude::*;
+
+        let mut total = 0;
+        let mut stale = Vec::new();
+
+        for path in paths {
+            let Ok(metadata) = std::fs::metadata(path) else {
+                continue;
+            };
+            let modified = metadata.modified().ok();
+
+            match self.entries.get(path) {
+                Some(cached)
+                    if cached.len == metadata.len()
+                        && cached.modified == modified
+                        && cached.model == model
+                        && cached.encoding == encoding =>
+                {
+                    total += cached.tokens;
+                }
+                _ => stale.push((path.to_path_buf(), metadata.len(), modified)),
+            }
+        }
+
+        let tokenizer = model.tokenizer();
+        let counted: Vec<(PathBuf, CachedCount)> = stale
+            .into_par_iter()
+            .map(|(path, len, modified)| {
+                let tokens = std::fs::read(&path)
+                    .ok()
+                    .and_then(|bytes| encoding::decode(bytes, &encoding).ok())
+                    .map_or_else(
+                        || usize::try_from(len / 4).unwrap_or(usize::MAX),
+                        |decoded| tokenizer.count_tokens(&decoded.text),
+                    );
+                let cached = CachedCount {
+                    len,
+                    modified,
+                    model,
+                    encoding,
+                    tokens,
+                };
+                (path, cached)
+            })
+            .collect();
+
+        for (path, cached) in counted {
+            total += cached.tokens;
+            self.entries.insert(path, cached);
+        }
+
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpe_counts_differ_from_estimate() {
+        let text = "fn main() { println!(\"héllo, wörld\"); }";
+
+        let cl100k = TokenizerModel::Cl100k.count_tokens(text);
+        let o200k = TokenizerModel::O200k.count_tokens(text);
+        let estimate = TokenizerModel::Estimate.count_tokens(text);
+
+        assert!(cl100k > 0);
+        assert!(o200k > 0);
+        assert_eq!(estimate, text.chars().count().div_ceil(4));
+        assert_eq!(TokenizerModel::Cl100k.count_tokens("hello world"), 2);
+    }
+
+    #[test]
+    fn test_model_names_round_trip() {
+        for model in TokenizerModel::ALL {
+            assert_eq!(TokenizerModel::from_name(model.name()), Some(model));
+        }
+        assert_eq!(TokenizerModel::from_name("gpt2"), None);
+    }
+
+    #[test]
+    fn test_file_token_cache() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        std::fs::write(&path, "hello world").unwrap();
+
+        let utf8 = EncodingConfig::default();
+        let mut cache = FileTokenCache::new();
+        assert_eq!(
+            cache.count_files([path.as_path()], TokenizerModel::Cl100k, utf8),
+            2
+        );
+        assert_eq!(
+            cache.count_files([path.as_path()], TokenizerModel::Estimate, utf8),
+            3
+        );
+
+        std::fs::write(&path, "hello world hello world").unwrap();
+        assert_eq!(
+            cache.count_files([path.as_path()], TokenizerModel::Cl100k, utf8),
+            4
+        );
+
+        // Files are decoded as the generator decodes them, not estimated from their bytes
+        let utf16 = temp_dir.path().join("utf16.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("hello world".encode_utf16().flat_map(u16::to_le_bytes));
+        std::fs::write(&utf16, bytes).unwrap();
+        assert_eq!(
+            cache.count_files([utf16.as_path()], TokenizerModel::Cl100k, utf8),
+            2
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Content transforms applied between reading files and building output
+//!
+//! Transforms only ever drop or shorten lines, so every kept line still knows
+//! its number in the original file. Line numbering runs afterwards and uses
+//! those original numbers.
+
+use crate::core::size_cap::format_count;
+use crate::core::{comments, outline};
+use serde::{Deserialize, Serialize};
+
+/// Which transforms run on file contents
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TransformConfig {
+    /// Strip line and block comments in supported languages
+    #[serde(default)]
+    pub strip_comments: bool,
+    /// Collapse runs of blank lines into one and drop leading and trailing ones
+    #[serde(default)]
+    pub collapse_blank_lines: bool,
+}
+
+impl TransformConfig {
+    /// Whether any transform is enabled
+    #[must_use]
+    pub const fn is_active(&self) -> bool {
+        self.strip_comments || self.collapse_blank_lines
+    }
+}
+
+/// File contents after the transforms ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transformed {
+    /// The transformed text
+    pub text: String,
+    /// Original 1-based line number of every line in `text`
+    pub line_numbers: Vec<usize>,
+    /// Whether function bodies were collapsed into an outline
+    pub outlined: bool,
+}
+
+/// Runs the enabled transforms on `content`, written in `language`
+///
+/// `language` is a name from `get_language_from_extension`. With `outline`,
+/// the file is reduced to an outline first. Comments are only stripped for
+/// languages the lexer knows; blank lines are collapsed for any file, except
+/// inside multi-line string literals. Returns `None` if nothing changed.
+#[must_use]
+pub fn apply(
+    content: &str,
+    language: &str,
+    config: &TransformConfig,
+    outline: bool,
+) -> Option<Transformed> {
+    let outlined = if outline {
+        outline::outline(content, language)
+    } else {
+        None
+    };
+    let Some(outlined) = outlined else {
+        return strip(content, language, *config);
+    };
+    match strip(&outlined.text, language, *config) {
+        Some(stripped) => Some(Transformed {
+            text: stripped.text,
+            line_numbers: stripped
+                .line_numbers
+                .iter()
+                .map(|&number| outlined.line_numbers[number - 1])
+                .collect(),
+            outlined: true,
+        }),
+        None => Some(outlined),
+    }
+}
+
+/// Strips comments and collapses blank lines as `config` asks
+fn strip(content: &str, language: &str, config: TransformConfig) -> Option<Transformed> {
+    if !config.is_active() {
+        return None;
+    }
+    let lexed = comments::lex(content, language, config.strip_comments);
+    let text = lexed.as_ref().map_or(content, |lexed| lexed.text.as_str());
+
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let originals: Vec<&str> = content.split_inclusive('\n').collect();
+    if lines.len() != originals.len() {
+        return None;
+    }
+
+    let mut kept: Vec<(usize, &str)> = Vec::with_capacity(lines.len());
+    let mut previous_blank = true;
+    for (index, (line, original)) in lines.iter().zip(&originals).enumerate() {
+        let in_literal = lexed
+            .as_ref()
+            .is_some_and(|lexed| lexed.literal_lines.get(index) == Some(&true));
+        let blank = line.trim().is_empty();
+        // Lines left blank by a removed comment go; real blank lines may stay
+        let keep = in_literal
+            || !blank
+            || (original.trim().is_empty() && !(config.collapse_blank_lines && previous_blank));
+        if keep {
+            kept.push((index + 1, line));
+            previous_blank = blank && !in_literal;
+        }
+    }
+    if config.collapse_blank_lines {
+        while kept.last().is_some_and(|(_, line)| line.trim().is_empty()) {
+            kept.pop();
+        }
+    }
+
+    let transformed = Transformed {
+        text: kept.iter().map(|(_, line)| *line).collect(),
+        line_numbers: kept.iter().map(|(number, _)| *number).collect(),
+        outlined: false,
+    };
+    (transformed.text != content).then_some(transformed)
+}
+
+/// Token savings of the transforms across an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct TransformReport {
+    /// Files whose contents changed
+    pub files_changed: usize,
+    /// Tokens in the changed files before the transforms
+    pub tokens_before: usize,
+    /// Tokens in the changed files after the transforms
+    pub tokens_after: usize,
+}
+
+impl TransformReport {
+    /// Tokens the transforms saved
+    #[must_use]
+    pub const fn tokens_saved(&self) -> usize {
+        self.tokens_before.saturating_sub(self.tokens_after)
+    }
+}
+
+impl std::fmt::Display for TransformReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = (self.tokens_saved() * 100)
+            .checked_div(self.tokens_before)
+            .unwrap_or_default();
+        write!(
+            f,
+            "Transforms saved {} tokens ({percent}%) in {} file(s)",
+            format_count(self.tokens_saved()),
+            self.files_changed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_keeps_original_line_numbers() {
+        let content = "// License header\n\nuse std::io;\n\n\n\nfn main() { // entry\n    let s = \"\n\n\";\n}\n\n";
+        let config = TransformConfig {
+            strip_comments: true,
+            collapse_blank_lines: true,
+        };
+
+        let transformed = apply(content, "rust", &config, false).unwrap();
+        assert_eq!(
+            transformed.text,
+            "use std::io;\n\nfn main() {\n    let s = \"\n\n\";\n}\n"
+        );
+        assert_eq!(transformed.line_numbers, vec![3, 4, 7, 8, 9, 10, 11]);
+
+        let comments_only = TransformConfig {
+            strip_comments: true,
+            ..TransformConfig::default()
+        };
+        assert_eq!(
+            apply(content, "rust", &comments_only, false).unwrap().text,
+            "\nuse std::io;\n\n\n\nfn main() {\n    let s = \"\n\n\";\n}\n\n"
+        );
+        assert!(apply("fn main() {}\n", "rust", &config, false).is_none());
+
+        let outlined = apply(content, "rust", &config, true).unwrap();
+        assert_eq!(outlined.text, "use std::io;\n\nfn main() { ... }\n");
+        assert_eq!(outlined.line_numbers, vec![3, 4, 7]);
+        assert!(outlined.outlined);
+    }
+}
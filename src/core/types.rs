@@ -2,6 +2,17 @@
 
 //! Redesigned type system for fsPrompt with improved expressiveness and type safety
 
+use crate::core::budget::TokenBudget;
+use crate::core::encoding::EncodingConfig;
+use crate::core::git_context::GitContextConfig;
+use crate::core::ignore::IgnorePatterns;
+use crate::core::line_numbers::LineNumbers;
+use crate::core::secrets::{SecretConfig, SecretFinding};
+use crate::core::size_cap::FileSizeCap;
+use crate::core::split::SplitLimit;
+use crate::core::template::OutputTemplate;
+use crate::core::tokenizer::{FileTokenCache, TokenizerModel};
+use crate::core::transform::TransformConfig;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
@@ -137,6 +148,12 @@ impl TokenCount {
         Self(chars.div_ceil(4))
     }
 
+    /// Counts tokens in `text` with the given model's tokenizer
+    #[must_use]
+    pub fn count(text: &str, model: TokenizerModel) -> Self {
+        Self(model.count_tokens(text))
+    }
+
     /// Gets the raw count
     #[must_use]
     pub const fn get(&self) -> usize {
@@ -236,9 +253,10 @@ impl FsEntry {
         }
     }
 
-    /// Check if this entry matches a pattern
-    pub fn matches(&self, pattern: &IgnorePattern) -> bool {
-        (pattern.compiled)(self.path.as_path())
+    /// Check if this entry is excluded by the patterns
+    #[must_use]
+    pub fn matches(&self, patterns: &IgnorePatterns) -> bool {
+        patterns.is_ignored(self.path.as_path(), self.is_dir())
     }
 }
 
@@ -254,6 +272,8 @@ pub enum SelectionState {
     Checked,
     /// Partially selected (directories with mixed children)
     Indeterminate,
+    /// Selected as an outline, with function bodies collapsed
+    Outline,
 }
 
 impl SelectionState {
@@ -274,99 +294,86 @@ impl SelectionState {
     }
 }
 
-// ===== Pattern Types =====
-
-/// Type of ignore pattern
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum PatternType {
-    /// Exact file/directory name
-    Exact,
-    /// Glob pattern (*, ?)
-    Glob,
-    /// Regular expression
-    Regex,
-}
-
-/// Compiled ignore pattern
-#[derive(Clone)]
-pub struct IgnorePattern {
-    /// Original pattern string
-    pub pattern: String,
-    /// Pattern type
-    pub pattern_type: PatternType,
-    /// Compiled pattern (opaque to avoid exposing regex)
-    compiled: Arc<dyn Fn(&Path) -> bool + Send + Sync>,
-}
-
-impl IgnorePattern {
-    /// Create from a pattern string, auto-detecting type
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if:
-    /// - The glob pattern is invalid
-    /// - The regex pattern is invalid
-    pub fn parse(pattern: &str) -> Result<Self, String> {
-        let pattern_type = if pattern.contains('*') || pattern.contains('?') {
-            PatternType::Glob
-        } else if pattern.starts_with('^') || pattern.ends_with('$') {
-            PatternType::Regex
-        } else {
-            PatternType::Exact
-        };
-
-        // Compile the pattern based on its type
-        let compiled = match pattern_type {
-            PatternType::Exact => {
-                let pattern = pattern.to_string();
-                Arc::new(move |path: &Path| -> bool {
-                    path.to_str().is_some_and(|p| p.contains(&pattern))
-                }) as Arc<dyn Fn(&Path) -> bool + Send + Sync>
-            }
-            PatternType::Glob => {
-                let glob_pattern = glob::Pattern::new(pattern)
-                    .map_err(|e| format!("Invalid glob pattern: {e}"))?;
-                Arc::new(move |path: &Path| -> bool {
-                    path.to_str().is_some_and(|p| glob_pattern.matches(p))
-                }) as Arc<dyn Fn(&Path) -> bool + Send + Sync>
-            }
-            PatternType::Regex => {
-                let regex = regex::Regex::new(pattern)
-                    .map_err(|e| format!("Invalid regex pattern: {e}"))?;
-                Arc::new(move |path: &Path| -> bool {
-                    path.to_str().is_some_and(|p| regex.is_match(p))
-                }) as Arc<dyn Fn(&Path) -> bool + Send + Sync>
-            }
-        };
-
-        Ok(Self {
-            pattern: pattern.to_string(),
-            pattern_type,
-            compiled,
-        })
-    }
-}
-
-impl std::fmt::Debug for IgnorePattern {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("IgnorePattern")
-            .field("pattern", &self.pattern)
-            .field("pattern_type", &self.pattern_type)
-            .field("compiled", &"<compiled>")
-            .finish()
-    }
-}
-
 // ===== Output Types =====
 
 /// Output format options
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+///
+/// Serialized as its name only; a template name is resolved against the
+/// loaded templates by `SerializableConfig::to_app_config`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub enum OutputFormat {
     /// XML format
     Xml,
     /// Markdown format  
     #[default]
     Markdown,
+    /// Structured JSON document
+    Json,
+    /// User-defined template loaded from the config directory
+    Template(Box<OutputTemplate>),
+}
+
+impl OutputFormat {
+    /// Name used in the config file and on the command line
+    #[must_use]
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Xml => "xml",
+            Self::Markdown => "markdown",
+            Self::Json => "json",
+            Self::Template(template) => &template.name,
+        }
+    }
+
+    /// Parses a format name, accepting `md` as shorthand for Markdown
+    ///
+    /// Names that are not built-in formats are looked up in `templates`.
+    #[must_use]
+    pub fn from_name(name: &str, templates: &[OutputTemplate]) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "xml" => Some(Self::Xml),
+            "markdown" | "md" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            _ => templates
+                .iter()
+                .find(|t| t.name == name)
+                .map(|t| Self::Template(Box::new(t.clone()))),
+        }
+    }
+
+    /// File extension for saved output
+    #[must_use]
+    pub fn extension(&self) -> &str {
+        match self {
+            Self::Xml => "xml",
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Template(template) => &template.extension,
+        }
+    }
+
+    /// Returns whether `name` selects a built-in format, in any case
+    #[must_use]
+    pub fn is_builtin_name(name: &str) -> bool {
+        Self::from_name(name, &[]).is_some()
+    }
+}
+
+impl Serialize for OutputFormat {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputFormat {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::from_name(&name, &[]).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "unknown output format {name:?}; templates are resolved when the config is loaded"
+            ))
+        })
+    }
 }
 
 /// File reading strategy
@@ -702,8 +709,8 @@ impl ClipboardContent {
     }
 
     /// Get the format
-    pub const fn format(&self) -> OutputFormat {
-        self.format
+    pub const fn format(&self) -> &OutputFormat {
+        &self.format
     }
 
     /// Convert to owned String
@@ -766,6 +773,24 @@ pub struct AppState {
     pub output: OutputState,
     /// Application configuration
     pub config: AppConfig,
+    /// Recent directories that still existed when last checked
+    pub existing_recent_directories: Vec<PathBuf>,
+}
+
+impl AppState {
+    /// Rechecks which recent directories still exist
+    ///
+    /// Called when the configuration loads or the recent list changes, so
+    /// the menus don't touch the disk on every frame.
+    pub fn refresh_recent_directories(&mut self) {
+        self.existing_recent_directories = self
+            .config
+            .recent_directories
+            .iter()
+            .filter(|path| path.is_dir())
+            .cloned()
+            .collect();
+    }
 }
 
 /// Tracks selections with undo/redo support
@@ -848,21 +873,66 @@ impl OutputSearch {
 
 /// Output generation state
 #[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)] // independent flags
 pub struct OutputState {
     /// Current output format
     pub format: OutputFormat,
-    /// Generated content
+    /// User-defined templates available as extra formats
+    pub templates: Vec<OutputTemplate>,
+    /// Generated content of the part being shown
     pub content: Option<Arc<String>>,
+    /// All generated parts (a single one unless the output was split)
+    pub parts: Vec<Arc<String>>,
+    /// Index of the part in `content`
+    pub current_part: usize,
     /// Token count
     pub tokens: Option<TokenCount>,
     /// Is generation in progress
     pub generating: bool,
     /// Estimated tokens for current selection (real-time)
     pub estimated_tokens: Option<usize>,
+    /// Whether the estimate is being recounted in the background
+    pub counting_tokens: bool,
+    /// Per-file token counts backing `estimated_tokens`
+    pub token_cache: FileTokenCache,
+    /// Secrets redacted from the generated content
+    pub secrets: Vec<SecretFinding>,
+    /// Whether the redacted secrets were reviewed before copying
+    pub secrets_reviewed: bool,
+    /// Whether the secret review dialog is open
+    pub show_secret_review: bool,
+}
+
+impl OutputState {
+    /// Stores freshly generated parts and shows the first one
+    pub fn set_parts(&mut self, parts: Vec<String>) {
+        self.parts = parts.into_iter().map(Arc::new).collect();
+        self.current_part = 0;
+        self.content = self.parts.first().cloned();
+    }
+
+    /// Clears generated output
+    pub fn clear(&mut self) {
+        self.parts.clear();
+        self.current_part = 0;
+        self.content = None;
+        self.tokens = None;
+        self.secrets.clear();
+        self.secrets_reviewed = false;
+        self.show_secret_review = false;
+    }
+
+    /// Shows the part at `index`, if it exists
+    pub fn show_part(&mut self, index: usize) {
+        if let Some(part) = self.parts.get(index) {
+            self.current_part = index;
+            self.content = Some(Arc::clone(part));
+        }
+    }
 }
 
 /// Application configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AppConfig {
     /// Window settings
     pub window: WindowConfig,
@@ -870,8 +940,40 @@ pub struct AppConfig {
     pub ui: UiConfig,
     /// Default ignore patterns
     pub ignore_patterns: Vec<String>,
+    /// Whether `.gitignore`, `.ignore`, `.fspromptignore` and `.git/info/exclude` hide files
+    #[serde(default = "default_true")]
+    pub respect_ignore_files: bool,
     /// Performance settings
     pub performance: PerformanceConfig,
+    /// Output generation settings
+    #[serde(default)]
+    pub output: OutputConfig,
+    /// Recently opened directories, most recent first
+    #[serde(default)]
+    pub recent_directories: Vec<PathBuf>,
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+impl AppConfig {
+    /// Maximum number of recent directories kept
+    pub const MAX_RECENT_DIRECTORIES: usize = 10;
+
+    /// Directory opened most recently
+    #[must_use]
+    pub fn last_directory(&self) -> Option<&Path> {
+        self.recent_directories.first().map(PathBuf::as_path)
+    }
+
+    /// Moves `path` to the front of the recent directories
+    pub fn add_recent_directory(&mut self, path: PathBuf) {
+        self.recent_directories.retain(|recent| recent != &path);
+        self.recent_directories.insert(0, path);
+        self.recent_directories
+            .truncate(Self::MAX_RECENT_DIRECTORIES);
+    }
 }
 
 /// Builder for `AppConfig`
@@ -880,7 +982,9 @@ pub struct AppConfigBuilder {
     window: Option<WindowConfig>,
     ui: Option<UiConfig>,
     ignore_patterns: Option<Vec<String>>,
+    respect_ignore_files: Option<bool>,
     performance: Option<PerformanceConfig>,
+    output: Option<OutputConfig>,
 }
 
 impl AppConfigBuilder {
@@ -891,7 +995,9 @@ impl AppConfigBuilder {
             window: None,
             ui: None,
             ignore_patterns: None,
+            respect_ignore_files: None,
             performance: None,
+            output: None,
         }
     }
 
@@ -937,6 +1043,14 @@ impl AppConfigBuilder {
         self
     }
 
+    /// Set whether ignore files are honoured
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Cannot be const due to mutation
+    pub fn respect_ignore_files(mut self, respect: bool) -> Self {
+        self.respect_ignore_files = Some(respect);
+        self
+    }
+
     /// Set performance configuration
     #[must_use]
     #[allow(clippy::missing_const_for_fn)] // Cannot be const due to mutation
@@ -945,6 +1059,14 @@ impl AppConfigBuilder {
         self
     }
 
+    /// Set output configuration
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Cannot be const due to mutation
+    pub fn output(mut self, output: OutputConfig) -> Self {
+        self.output = Some(output);
+        self
+    }
+
     /// Build the final `AppConfig`
     #[must_use]
     pub fn build(self) -> AppConfig {
@@ -962,7 +1084,10 @@ impl AppConfigBuilder {
                     "_*".to_string(),
                 ]
             }),
+            respect_ignore_files: self.respect_ignore_files.unwrap_or(true),
             performance: self.performance.unwrap_or_default(),
+            output: self.output.unwrap_or_default(),
+            recent_directories: Vec::new(),
         }
     }
 }
@@ -981,13 +1106,17 @@ impl Default for AppConfig {
                 "dist".to_string(),
                 "_*".to_string(),
             ],
+            respect_ignore_files: true,
             performance: PerformanceConfig::default(),
+            output: OutputConfig::default(),
+            recent_directories: Vec::new(),
         }
     }
 }
 
 /// Window configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct WindowConfig {
     /// Window width
     pub width: f32,
@@ -1071,7 +1200,8 @@ impl Default for WindowConfig {
 }
 
 /// UI configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(clippy::struct_excessive_bools)] // independent preferences
 pub struct UiConfig {
     /// Theme preference
     pub theme: Theme,
@@ -1081,9 +1211,15 @@ pub struct UiConfig {
     pub show_hidden: bool,
     /// Include directory tree in output
     pub include_tree: bool,
+    /// Last used output format
+    #[serde(default)]
+    pub output_format: OutputFormat,
     /// Show advanced settings panel
     #[serde(default)]
     pub show_settings: bool,
+    /// Reopen the last directory on startup
+    #[serde(default = "default_true")]
+    pub reopen_last_directory: bool,
 }
 
 impl Default for UiConfig {
@@ -1093,13 +1229,16 @@ impl Default for UiConfig {
             font_size: 12.0,
             show_hidden: false,
             include_tree: false,
+            output_format: OutputFormat::default(),
             show_settings: false,
+            reopen_last_directory: true,
         }
     }
 }
 
 /// Performance configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PerformanceConfig {
     /// Maximum concurrent file reads
     pub max_concurrent_reads: usize,
@@ -1119,6 +1258,41 @@ impl Default for PerformanceConfig {
     }
 }
 
+/// Output generation configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// Tokenizer used for token counts
+    #[serde(default)]
+    pub tokenizer: TokenizerModel,
+    /// Token budget the selection is packed into
+    #[serde(default)]
+    pub token_budget: TokenBudget,
+    /// Limit for splitting output into numbered parts
+    #[serde(default)]
+    pub split: SplitLimit,
+    /// Include binary files as placeholder entries instead of skipping them
+    #[serde(default)]
+    pub binary_placeholders: bool,
+    /// How file contents are decoded
+    #[serde(default)]
+    pub encoding: EncodingConfig,
+    /// Secret redaction
+    #[serde(default)]
+    pub secrets: SecretConfig,
+    /// Cap on the size of a single file
+    #[serde(default)]
+    pub size_cap: FileSizeCap,
+    /// Content transforms such as comment stripping
+    #[serde(default)]
+    pub transforms: TransformConfig,
+    /// Line numbers in file contents
+    #[serde(default)]
+    pub line_numbers: LineNumbers,
+    /// Git branch, commits and diff section
+    #[serde(default)]
+    pub git_context: GitContextConfig,
+}
+
 /// UI Theme options
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Theme {
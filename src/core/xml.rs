@@ -0,0 +1,185 @@
+//! Well-formed XML writing for the XML output format
+//!
+//! Attribute values are escaped and CDATA sections are split around `]]>`, so
+//! arbitrary paths and file contents cannot break the document. Characters
+//! XML 1.0 does not allow anywhere (most C0 controls, U+FFFE and U+FFFF) are
+//! replaced with U+FFFD.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+
+/// Indentation per nesting level
+const INDENT: &str = "  ";
+
+/// Appends XML to a string, one element per line
+#[derive(Debug)]
+pub struct XmlWriter<'a> {
+    output: &'a mut String,
+}
+
+impl<'a> XmlWriter<'a> {
+    /// Creates a writer appending to `output`
+    pub const fn new(output: &'a mut String) -> Self {
+        Self { output }
+    }
+
+    /// Writes the XML declaration
+    pub fn declaration(&mut self) {
+        self.output
+            .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    }
+
+    /// Writes a start tag at nesting level `depth`
+    pub fn start(&mut self, depth: usize, name: &str, attributes: &[(&str, &dyn Display)]) {
+        self.tag(depth, name, attributes);
+        self.output.push_str(">\n");
+    }
+
+    /// Writes a self-closing element at nesting level `depth`
+    pub fn empty(&mut self, depth: usize, name: &str, attributes: &[(&str, &dyn Display)]) {
+        self.tag(depth, name, attributes);
+        self.output.push_str("/>\n");
+    }
+
+    /// Writes an end tag at nesting level `depth`
+    pub fn end(&mut self, depth: usize, name: &str) {
+        self.indent(depth);
+        self.output.push_str("</");
+        self.output.push_str(name);
+        self.output.push_str(">\n");
+    }
+
+    /// Writes `text` as a CDATA section starting and ending on its own line
+    ///
+    /// Every `]]>` in the text ends the section after `]]` and starts a new one
+    /// before `>`, so parsers read the text back unchanged.
+    pub fn cdata(&mut self, text: &str) {
+        self.output.push_str("<![CDATA[\n");
+        let mut rest = text;
+        while let Some(index) = rest.find("]]>") {
+            push_xml_chars(self.output, &rest[..index + 2]);
+            self.output.push_str("]]><![CDATA[");
+            rest = &rest[index + 2..];
+        }
+        push_xml_chars(self.output, rest);
+        if !text.ends_with('\n') {
+            self.output.push('\n');
+        }
+        self.output.push_str("]]>\n");
+    }
+
+    /// Writes an empty line between sections
+    pub fn blank_line(&mut self) {
+        self.output.push('\n');
+    }
+
+    /// Closes the document without a trailing newline
+    pub fn finish(self, name: &str) {
+        self.output.push_str("</");
+        self.output.push_str(name);
+        self.output.push('>');
+    }
+
+    fn tag(&mut self, depth: usize, name: &str, attributes: &[(&str, &dyn Display)]) {
+        self.indent(depth);
+        self.output.push('<');
+        self.output.push_str(name);
+        for (key, value) in attributes {
+            self.output.push(' ');
+            self.output.push_str(key);
+            self.output.push_str("=\"");
+            self.output.push_str(&escape_attribute(&value.to_string()));
+            self.output.push('"');
+        }
+    }
+
+    fn indent(&mut self, depth: usize) {
+        for _ in 0..depth {
+            self.output.push_str(INDENT);
+        }
+    }
+}
+
+/// Returns whether XML 1.0 allows `c` in a document
+#[must_use]
+pub const fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{fffd}' | '\u{10000}'..)
+}
+
+/// Escapes a value for use inside a double-quoted attribute
+///
+/// Tabs and line breaks become character references, since parsers would
+/// otherwise normalize them to spaces.
+#[must_use]
+pub fn escape_attribute(value: &str) -> Cow<'_, str> {
+    let needs_escaping =
+        |c: char| matches!(c, '&' | '<' | '>' | '"' | '\t' | '\n' | '\r') || !is_xml_char(c);
+    if !value.chars().any(needs_escaping) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut escaped = String::with_capacity(value.len() + 16);
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' => escaped.push_str("&#9;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            c if !is_xml_char(c) => escaped.push(char::REPLACEMENT_CHARACTER),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// Appends `text`, replacing characters XML does not allow
+fn push_xml_chars(output: &mut String, text: &str) {
+    if text.chars().all(is_xml_char) {
+        output.push_str(text);
+    } else {
+        output.extend(text.chars().map(|c| {
+            if is_xml_char(c) {
+                c
+            } else {
+                char::REPLACEMENT_CHARACTER
+            }
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escaped_document_round_trips() {
+        let path = "a&b/<weird> \"name\"\n.rs";
+        let content = "if a[b[0]]>c { x }\n]]>\u{1}<![CDATA[ & </file>";
+
+        let mut output = String::new();
+        let mut writer = XmlWriter::new(&mut output);
+        writer.declaration();
+        writer.start(0, "codebase", &[]);
+        writer.start(1, "file", &[("path", &path), ("lines", &3)]);
+        writer.cdata(content);
+        writer.end(1, "file");
+        writer.finish("codebase");
+
+        let document = roxmltree::Document::parse(&output).unwrap();
+        let file = document
+            .root_element()
+            .children()
+            .find(|node| node.has_tag_name("file"))
+            .unwrap();
+        assert_eq!(file.attribute("path"), Some(path));
+        assert_eq!(file.attribute("lines"), Some("3"));
+
+        // The section starts on its own line and the writer adds a final newline
+        let text: String = file.children().filter_map(|node| node.text()).collect();
+        let content = content.replace('\u{1}', "\u{fffd}");
+        assert_eq!(text, format!("\n\n{content}\n\n  "));
+    }
+}
@@ -3,6 +3,7 @@
 use crate::app::FsPromptApp;
 use crate::core::types::CanonicalPath;
 use eframe::egui;
+use std::path::Path;
 
 impl FsPromptApp {
     /// Handles global keyboard shortcuts
@@ -61,14 +62,14 @@ impl FsPromptApp {
             // Ctrl+A for Select All (when in file tree context)
             if i.modifiers.ctrl && i.key_pressed(egui::Key::A) && self.state.root.is_some() {
                 self.tree.select_all();
-                self.state.output.estimated_tokens = Some(self.estimate_tokens_for_selection());
+                self.refresh_token_estimate();
                 self.record_state();
             }
 
             // Ctrl+D for Deselect All
             if i.modifiers.ctrl && i.key_pressed(egui::Key::D) && self.state.root.is_some() {
                 self.tree.deselect_all();
-                self.state.output.estimated_tokens = Some(0);
+                self.refresh_token_estimate();
                 self.record_state();
             }
 
@@ -82,11 +83,34 @@ impl FsPromptApp {
     /// Handles directory selection dialog
     pub fn handle_directory_selection(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_folder() {
-            if let Ok(canonical_path) = CanonicalPath::new(&path) {
+            self.open_directory(&path);
+        }
+    }
+
+    /// Opens `path` as the root directory and adds it to the recent directories
+    ///
+    /// The selection of the previous root is saved, and the one the new root
+    /// was last left with restored. Without one, the project defaults apply.
+    pub fn open_directory(&mut self, path: &Path) {
+        match CanonicalPath::new(path) {
+            Ok(canonical_path) => {
+                self.remember_selection();
+                // Related files found under the previous root must not be selected here
+                self.related_files.running = false;
                 self.state.root = Some(canonical_path.clone());
+                self.load_project_config(&canonical_path);
                 self.tree
-                    .set_ignore_patterns(&self.state.config.ignore_patterns.join(","));
+                    .set_ignore_patterns(&self.ignore_patterns().join(","));
+                self.tree
+                    .set_respect_ignore_files(self.state.config.respect_ignore_files);
                 self.tree.set_root(canonical_path.clone());
+                self.refresh_git_status();
+                self.refresh_token_estimate();
+                if !self.restore_last_selection() {
+                    self.select_project_defaults();
+                }
+                self.update_query_preview();
+                self.update_content_search();
 
                 // Start watching the directory
                 if let Err(e) = self.fs_watcher.watch(&canonical_path) {
@@ -95,11 +119,20 @@ impl FsPromptApp {
                 }
 
                 self.files_changed = false;
+                self.state
+                    .config
+                    .add_recent_directory(canonical_path.as_path().to_path_buf());
+                self.state.refresh_recent_directories();
+                self.save_config();
                 self.toast_manager.success(format!(
                     "Loaded {}",
                     path.file_name().unwrap_or_default().to_string_lossy()
                 ));
             }
+            Err(e) => {
+                self.toast_manager
+                    .error(format!("Failed to open {}: {e}", path.display()));
+            }
         }
     }
 }
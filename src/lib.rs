@@ -15,6 +15,7 @@
 //! This library provides the core functionality for generating context prompts from codebases.
 
 pub mod app;
+pub mod cli;
 pub mod core;
 pub mod handlers;
 pub mod state;
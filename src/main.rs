@@ -14,10 +14,12 @@
 //!
 //! This application allows users to generate context prompts from codebases,
 //! producing XML or Markdown output containing directory structure and file contents.
+//! Run `fsprompt export --help` for the headless command-line mode.
 
 use eframe::egui;
 
 pub mod app;
+pub mod cli;
 pub mod core;
 pub mod handlers;
 pub mod state;
@@ -63,9 +65,16 @@ fn load_icon() -> egui::IconData {
 }
 
 fn main() -> eframe::Result<()> {
+    // Headless mode: `fsprompt export <ROOT> [OPTIONS]` never opens a window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "export") {
+        std::process::exit(cli::run(&args[1..]));
+    }
+
+    let window = state::ConfigManager::new().load().window;
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1400.0, 900.0])
+            .with_inner_size([window.width, window.height])
             .with_min_inner_size([640.0, 480.0])
             .with_icon(load_icon()),
         ..Default::default()
@@ -89,6 +98,9 @@ impl eframe::App for FsPromptApp {
 
         // Process worker events
         self.process_worker_events(ctx);
+        self.process_search_events(ctx);
+        self.process_token_events(ctx);
+        self.process_git_status_events(ctx);
 
         // Check for filesystem changes
         self.check_fs_changes(ctx);
@@ -100,16 +112,27 @@ impl eframe::App for FsPromptApp {
         // Global keyboard shortcuts
         self.handle_keyboard_shortcuts(ctx);
 
+        // Remember the window size for the next session
+        if let Some(rect) = ctx.input(|i| i.viewport().inner_rect) {
+            self.state.config.window.width = rect.width();
+            self.state.config.window.height = rect.height();
+        }
+
         // Show app header
         let mut directory_selected = false;
+        let mut recent_directory = None;
 
         AppHeader::new(&mut self.state, &mut self.icon_manager)
             .on_select_directory(|| directory_selected = true)
+            .on_open_recent(|path| recent_directory = Some(path))
             .show(ctx);
 
         if directory_selected {
             self.handle_directory_selection();
         }
+        if let Some(path) = recent_directory {
+            self.open_directory(&path);
+        }
 
         // Show welcome screen if no directory is selected
         if self.state.root.is_none() {
@@ -171,6 +194,11 @@ impl eframe::App for FsPromptApp {
             });
         }
 
+        // Show the secret review dialog before copying
+        if self.state.output.show_secret_review {
+            self.show_secret_review(ctx);
+        }
+
         // Show toast notifications
         self.toast_manager.show_ui(ctx);
 
@@ -237,6 +265,39 @@ impl FsPromptApp {
             {
                 self.handle_directory_selection();
             }
+
+            self.show_recent_directories(ui);
         });
     }
+
+    /// Lists the recently opened directories that still exist
+    fn show_recent_directories(&mut self, ui: &mut egui::Ui) {
+        let tokens = UiTheme::design_tokens(ui.visuals().dark_mode);
+        let recent = self.state.existing_recent_directories.clone();
+        if recent.is_empty() {
+            return;
+        }
+
+        ui.add_space(tokens.spacing.xl);
+        ui.label(
+            egui::RichText::new("Recent directories")
+                .size(tokens.typography.body_large.size)
+                .color(tokens.colors.on_surface_variant),
+        );
+        ui.add_space(tokens.spacing.sm);
+
+        for path in recent {
+            let name = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            if ui
+                .link(name)
+                .on_hover_text(path.display().to_string())
+                .clicked()
+            {
+                self.open_directory(&path);
+            }
+        }
+    }
 }
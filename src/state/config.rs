@@ -1,49 +1,84 @@
 //! Configuration persistence for fsPrompt
+//!
+//! The config file is versioned. Version 1 files have no `version` field and
+//! a flat layout that dropped several settings; they are read as
+//! [`LegacyConfig`], migrated, and rewritten in the current layout on the
+//! next save. The original is kept next to it as `config.v1.json`.
 
-use crate::core::types::{AppConfig, Theme};
+use crate::core::template::{load_templates, OutputTemplate};
+use crate::core::types::{
+    AppConfig, OutputConfig, OutputFormat, PerformanceConfig, Theme, UiConfig, WindowConfig,
+};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Version of the config file layout written by this build
+pub const CONFIG_VERSION: u32 = 2;
 
 /// Serializable configuration for persistence
-/// This is a separate type to maintain backward compatibility
-/// and handle legacy config migrations
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Mirrors `AppConfig`, except that the output format is stored by name so
+/// that formats naming a user template survive changes to the template.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SerializableConfig {
-    /// Window dimensions
-    pub window_width: f32,
-    /// Window height in pixels
-    pub window_height: f32,
-
-    /// Split position (0.0 to 1.0)
-    pub split_position: f32,
-
-    /// Last opened directory
-    pub last_directory: Option<PathBuf>,
-
-    /// Ignore patterns (comma-separated)
-    pub ignore_patterns: String,
+    /// Layout version: [`CONFIG_VERSION`] when written, lower when migrated
+    pub version: u32,
+    /// Window size and pane split
+    pub window: WindowConfig,
+    /// UI preferences
+    pub ui: SerializableUiConfig,
+    /// Ignore patterns
+    pub ignore_patterns: Vec<String>,
+    /// Whether `.gitignore` and the other ignore files hide files
+    pub respect_ignore_files: bool,
+    /// Performance settings
+    pub performance: PerformanceConfig,
+    /// Output generation settings
+    pub output: OutputConfig,
+    /// Recently opened directories, most recent first
+    pub recent_directories: Vec<PathBuf>,
+}
 
+/// UI preferences as stored in the config file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerializableUiConfig {
+    /// Theme preference
+    pub theme: Theme,
+    /// Font size
+    pub font_size: f32,
+    /// Show hidden files by default
+    pub show_hidden: bool,
     /// Include directory tree in output
     pub include_tree: bool,
-
-    /// Last used output format
+    /// Name of the output format: xml, markdown, json or a template name
     pub output_format: String,
-
-    /// Theme preference: "auto", "light", "dark"
-    pub theme: String,
+    /// Reopen the last directory on startup
+    pub reopen_last_directory: bool,
 }
 
 impl Default for SerializableConfig {
     fn default() -> Self {
+        Self::from(&AppConfig::default())
+    }
+}
+
+impl Default for SerializableUiConfig {
+    fn default() -> Self {
+        Self::from(&UiConfig::default())
+    }
+}
+
+impl From<&UiConfig> for SerializableUiConfig {
+    fn from(ui: &UiConfig) -> Self {
         Self {
-            window_width: 1200.0,
-            window_height: 800.0,
-            split_position: 0.3,
-            last_directory: None,
-            ignore_patterns: String::new(),
-            include_tree: false,
-            output_format: "markdown".to_string(),
-            theme: "auto".to_string(),
+            theme: ui.theme,
+            font_size: ui.font_size,
+            show_hidden: ui.show_hidden,
+            include_tree: ui.include_tree,
+            output_format: ui.output_format.name().to_string(),
+            reopen_last_directory: ui.reopen_last_directory,
         }
     }
 }
@@ -51,55 +86,108 @@ impl Default for SerializableConfig {
 impl From<&AppConfig> for SerializableConfig {
     fn from(config: &AppConfig) -> Self {
         Self {
-            window_width: config.window.width,
-            window_height: config.window.height,
-            split_position: config.window.left_pane_ratio,
-            last_directory: None, // This should come from app state
-            ignore_patterns: config.ignore_patterns.join(","),
-            include_tree: true,               // This should come from output state
-            output_format: "xml".to_string(), // This should come from output state
-            theme: match config.ui.theme {
-                Theme::Light => "light".to_string(),
-                Theme::Dark => "dark".to_string(),
-                Theme::System => "auto".to_string(),
-            },
+            version: CONFIG_VERSION,
+            window: config.window.clone(),
+            ui: SerializableUiConfig::from(&config.ui),
+            ignore_patterns: config.ignore_patterns.clone(),
+            respect_ignore_files: config.respect_ignore_files,
+            performance: config.performance.clone(),
+            output: config.output.clone(),
+            recent_directories: config.recent_directories.clone(),
         }
     }
 }
 
 impl SerializableConfig {
-    /// Convert to `AppConfig` with defaults for missing fields
-    pub fn to_app_config(&self) -> AppConfig {
-        crate::core::types::AppConfig {
-            window: crate::core::types::WindowConfig {
-                width: self.window_width,
-                height: self.window_height,
-                left_pane_ratio: self.split_position,
+    /// Parses a config file of any version
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the contents are not a valid config file
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(content).map_err(|e| format!("Invalid config file: {e}"))?;
+        let config = if value.get("version").is_some() {
+            serde_json::from_value(value)
+        } else {
+            serde_json::from_value::<LegacyConfig>(value).map(Self::from)
+        };
+        config.map_err(|e| format!("Invalid config file: {e}"))
+    }
+
+    /// Convert to `AppConfig`
+    ///
+    /// `templates` are used to resolve an output format that names a user template.
+    pub fn to_app_config(&self, templates: &[OutputTemplate]) -> AppConfig {
+        AppConfig {
+            window: self.window.clone(),
+            ui: UiConfig {
+                theme: self.ui.theme,
+                font_size: self.ui.font_size,
+                show_hidden: self.ui.show_hidden,
+                include_tree: self.ui.include_tree,
+                output_format: OutputFormat::from_name(&self.ui.output_format, templates)
+                    .unwrap_or_default(),
+                show_settings: false,
+                reopen_last_directory: self.ui.reopen_last_directory,
             },
-            ui: crate::core::types::UiConfig {
-                theme: match self.theme.as_str() {
+            ignore_patterns: self.ignore_patterns.clone(),
+            respect_ignore_files: self.respect_ignore_files,
+            performance: self.performance.clone(),
+            output: self.output.clone(),
+            recent_directories: self.recent_directories.clone(),
+        }
+    }
+}
+
+/// Version 1 config file, read only to migrate it
+///
+/// It had no `version` field and a flat layout. Only the fields below held
+/// the user's settings; `last_directory`, `include_tree` and `output_format`
+/// were always written with fixed values, so they are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacyConfig {
+    /// Window dimensions
+    pub window_width: f32,
+    /// Window height in pixels
+    pub window_height: f32,
+
+    /// Split position (0.0 to 1.0)
+    pub split_position: f32,
+
+    /// Ignore patterns (comma-separated)
+    pub ignore_patterns: String,
+
+    /// Theme preference: "auto", "light", "dark"
+    pub theme: String,
+}
+
+impl From<LegacyConfig> for SerializableConfig {
+    fn from(legacy: LegacyConfig) -> Self {
+        let defaults = Self::default();
+        Self {
+            version: 1,
+            window: WindowConfig {
+                width: legacy.window_width,
+                height: legacy.window_height,
+                left_pane_ratio: legacy.split_position,
+            },
+            ui: SerializableUiConfig {
+                theme: match legacy.theme.as_str() {
                     "light" => Theme::Light,
                     "dark" => Theme::Dark,
                     _ => Theme::System,
                 },
-                font_size: 14.0,    // Default
-                show_hidden: false, // Default
-                include_tree: self.include_tree,
-                show_settings: false, // Default
-            },
-            ignore_patterns: if self.ignore_patterns.is_empty() {
-                Vec::new()
-            } else {
-                self.ignore_patterns
-                    .split(',')
-                    .map(|s| s.trim().to_string())
-                    .collect()
-            },
-            performance: crate::core::types::PerformanceConfig {
-                max_concurrent_reads: 10, // Default
-                cache_size_mb: 100,       // Default
-                use_mmap: true,           // Default
+                ..defaults.ui
             },
+            ignore_patterns: legacy
+                .ignore_patterns
+                .split(',')
+                .map(str::trim)
+                .filter(|pattern| !pattern.is_empty())
+                .map(str::to_string)
+                .collect(),
+            ..defaults
         }
     }
 }
@@ -131,14 +219,51 @@ impl ConfigManager {
         Self::default()
     }
 
+    /// Creates a config manager keeping its files in `dir`
+    pub fn in_dir(dir: &Path) -> Self {
+        Self {
+            config_path: dir.join("config.json"),
+        }
+    }
+
     /// Load configuration from disk, returns default if not found or invalid
+    ///
+    /// An older config file is migrated and a copy of it kept next to the
+    /// current one, since the next save overwrites it.
     pub fn load(&self) -> AppConfig {
-        std::fs::read_to_string(&self.config_path)
+        let (templates, _) = self.load_templates();
+        let Some(serializable) = std::fs::read_to_string(&self.config_path)
             .ok()
-            .and_then(|content| serde_json::from_str::<SerializableConfig>(&content).ok())
-            .map_or_else(AppConfig::default, |serializable| {
-                serializable.to_app_config()
-            })
+            .and_then(|content| SerializableConfig::parse(&content).ok())
+        else {
+            return AppConfig::default();
+        };
+        if serializable.version < CONFIG_VERSION {
+            let backup = self
+                .config_path
+                .with_file_name(format!("config.v{}.json", serializable.version));
+            if !backup.exists() {
+                let _ = std::fs::copy(&self.config_path, backup);
+            }
+        }
+        serializable.to_app_config(&templates)
+    }
+
+    /// Directory holding user-defined output templates, next to `config.json`
+    pub fn templates_dir(&self) -> PathBuf {
+        self.config_path
+            .parent()
+            .map_or_else(|| PathBuf::from("templates"), |dir| dir.join("templates"))
+    }
+
+    /// File holding the named selection sets, next to `config.json`
+    pub fn selections_path(&self) -> PathBuf {
+        self.config_path.with_file_name("selections.json")
+    }
+
+    /// Load user-defined output templates along with errors for invalid files
+    pub fn load_templates(&self) -> (Vec<OutputTemplate>, Vec<String>) {
+        load_templates(&self.templates_dir())
     }
 
     /// Save configuration to disk
@@ -155,3 +280,65 @@ impl ConfigManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::template::OutputTemplate;
+
+    #[test]
+    fn test_config_round_trip_and_migration() {
+        let template = OutputTemplate::parse("review", "file = \"{content}\"").unwrap();
+        let mut config = AppConfig::default();
+        config.window.width = 1600.0;
+        config.ui.theme = Theme::Light;
+        config.ui.font_size = 16.0;
+        config.ui.show_hidden = true;
+        config.ui.include_tree = true;
+        config.ui.output_format = OutputFormat::Template(Box::new(template.clone()));
+        config.ui.reopen_last_directory = false;
+        config.ignore_patterns = vec!["/build".to_string(), "!keep.log".to_string()];
+        config.respect_ignore_files = false;
+        config.performance.use_mmap = true;
+        config.output.token_budget.enabled = true;
+        config.output.git_context.commit_count = 3;
+        config.add_recent_directory(PathBuf::from("/tmp/a"));
+        config.add_recent_directory(PathBuf::from("/tmp/b"));
+        config.add_recent_directory(PathBuf::from("/tmp/a"));
+        assert_eq!(
+            config.last_directory(),
+            Some(std::path::Path::new("/tmp/a"))
+        );
+        assert_eq!(config.recent_directories.len(), 2);
+
+        let json = serde_json::to_string(&SerializableConfig::from(&config)).unwrap();
+        let parsed = SerializableConfig::parse(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&config.ui).unwrap()["output_format"],
+            "review"
+        );
+        assert_eq!(parsed.version, CONFIG_VERSION);
+        assert_eq!(parsed.to_app_config(&[template]), config);
+
+        let legacy = r#"{
+            "window_width": 1000.0, "window_height": 700.0, "split_position": 0.4,
+            "last_directory": null, "ignore_patterns": "target, *.log",
+            "include_tree": true, "output_format": "xml", "theme": "dark"
+        }"#;
+        let migrated = SerializableConfig::parse(legacy).unwrap();
+        assert_eq!(migrated.version, 1);
+        let config = migrated.to_app_config(&[]);
+        assert_eq!(config.window.left_pane_ratio, 0.4);
+        assert_eq!(config.ui.theme, Theme::Dark);
+        assert_eq!(config.ignore_patterns, vec!["target", "*.log"]);
+
+        // Values version 1 always wrote the same are not taken as the user's choice
+        let defaults = AppConfig::default();
+        assert_eq!(config.ui.output_format, defaults.ui.output_format);
+        assert_eq!(config.ui.include_tree, defaults.ui.include_tree);
+        assert_eq!(config.ui.font_size, defaults.ui.font_size);
+        assert_eq!(config.output, defaults.output);
+        assert!(config.respect_ignore_files);
+        assert!(config.recent_directories.is_empty());
+    }
+}
@@ -8,6 +8,8 @@ use std::collections::HashSet;
 pub struct SelectionSnapshot {
     /// Set of selected file paths
     pub selected_files: HashSet<String>,
+    /// Set of file paths selected as an outline
+    pub outline_files: HashSet<String>,
     /// Set of expanded directories
     pub expanded_dirs: HashSet<String>,
 }
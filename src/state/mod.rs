@@ -2,6 +2,10 @@
 
 pub mod config;
 pub mod history;
+pub mod project;
+pub mod selections;
 
 pub use config::ConfigManager;
 pub use history::{HistoryManager, SelectionSnapshot};
+pub use project::{ProjectConfig, ProjectLayer};
+pub use selections::{SelectionSet, SelectionStore, SetOperation};
@@ -0,0 +1,288 @@
+//! Project-local configuration
+//!
+//! A `.fsprompt.toml` or `.fsprompt.json` in the opened root holds the export
+//! recipe a team shares for a repository. While that root is open, its values
+//! are layered over the user configuration: project ignore patterns apply
+//! after the user's, and every other value it sets replaces the user's. The
+//! replaced user values are kept so they, not the project's, are saved.
+
+use crate::core::budget::TokenBudget;
+use crate::core::template::OutputTemplate;
+use crate::core::types::{AppConfig, OutputFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// File names looked up in the root, in order of preference
+pub const PROJECT_CONFIG_NAMES: [&str; 2] = [".fsprompt.toml", ".fsprompt.json"];
+
+/// Settings read from a project config file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Ignore patterns applied after the user's
+    pub ignore_patterns: Vec<String>,
+    /// Output format: xml, markdown, json or a template name
+    pub format: Option<String>,
+    /// Whether the directory tree is included
+    pub include_tree: Option<bool>,
+    /// Globs of the files selected when the root is opened, as for `--include`
+    pub select: Vec<String>,
+    /// Output templates defined in the file, keyed by name
+    pub templates: BTreeMap<String, OutputTemplate>,
+    /// Token budget the selection is packed into
+    pub token_budget: Option<TokenBudget>,
+}
+
+impl ProjectConfig {
+    /// Parses a project config, as TOML or JSON depending on `file_name`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the file if the contents are invalid
+    pub fn parse(file_name: &str, source: &str) -> Result<Self, String> {
+        let parsed = if Path::new(file_name)
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            serde_json::from_str(source).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(source).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|e| format!("Invalid {file_name}: {e}"))
+    }
+
+    /// Reads the project config in `root`, returning it with the path it was read from
+    ///
+    /// Returns `Ok(None)` if the root has no project config.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is invalid
+    pub fn load(root: &Path) -> Result<Option<(PathBuf, Self)>, String> {
+        let Some(path) = PROJECT_CONFIG_NAMES
+            .iter()
+            .map(|name| root.join(name))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let source = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {file_name}: {e}"))?;
+        Self::parse(&file_name, &source).map(|config| Some((path, config)))
+    }
+
+    /// Adds the project templates to `templates`, replacing user templates of the same name
+    ///
+    /// Returns a warning for each template skipped because a built-in format
+    /// has its name.
+    #[must_use]
+    pub fn merge_templates(&self, templates: &mut Vec<OutputTemplate>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for (name, template) in &self.templates {
+            if OutputFormat::is_builtin_name(name) {
+                warnings.push(format!(
+                    "Project template {name} is skipped: its name is taken by a built-in format"
+                ));
+                continue;
+            }
+            templates.retain(|existing| &existing.name != name);
+            templates.push(OutputTemplate {
+                name: name.clone(),
+                ..template.clone()
+            });
+        }
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        warnings
+    }
+
+    /// Resolves the project format against `templates`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the format names neither a built-in format nor a template
+    pub fn output_format(
+        &self,
+        templates: &[OutputTemplate],
+    ) -> Result<Option<OutputFormat>, String> {
+        self.format
+            .as_deref()
+            .map(|name| {
+                OutputFormat::from_name(name, templates)
+                    .ok_or_else(|| format!("Unknown format in project config: {name}"))
+            })
+            .transpose()
+    }
+}
+
+/// A project config applied over the user configuration
+#[derive(Debug, Clone)]
+pub struct ProjectLayer {
+    /// File the settings were read from
+    pub path: PathBuf,
+    /// The project settings
+    pub config: ProjectConfig,
+    /// User values the project replaced
+    replaced: ReplacedValues,
+}
+
+/// User values set aside while a project config is applied
+#[derive(Debug, Clone, Default)]
+struct ReplacedValues {
+    output_format: Option<OutputFormat>,
+    include_tree: Option<bool>,
+    token_budget: Option<TokenBudget>,
+}
+
+impl ProjectLayer {
+    /// Applies `project` over `config` and `templates`
+    ///
+    /// Returns the layer together with a warning for each value that could
+    /// not be applied.
+    pub fn apply(
+        path: PathBuf,
+        project: ProjectConfig,
+        config: &mut AppConfig,
+        templates: &mut Vec<OutputTemplate>,
+    ) -> (Self, Vec<String>) {
+        let mut warnings = project.merge_templates(templates);
+        let mut replaced = ReplacedValues::default();
+
+        match project.output_format(templates) {
+            Ok(Some(format)) => {
+                replaced.output_format =
+                    Some(std::mem::replace(&mut config.ui.output_format, format));
+            }
+            Ok(None) => {}
+            Err(e) => warnings.push(e),
+        }
+        if let Some(include_tree) = project.include_tree {
+            replaced.include_tree =
+                Some(std::mem::replace(&mut config.ui.include_tree, include_tree));
+        }
+        if let Some(budget) = project.token_budget {
+            replaced.token_budget =
+                Some(std::mem::replace(&mut config.output.token_budget, budget));
+        }
+
+        let layer = Self {
+            path,
+            config: project,
+            replaced,
+        };
+        (layer, warnings)
+    }
+
+    /// Puts the user values the project replaced back into `config`
+    pub fn restore(&self, config: &mut AppConfig) {
+        if let Some(format) = &self.replaced.output_format {
+            config.ui.output_format = format.clone();
+        }
+        if let Some(include_tree) = self.replaced.include_tree {
+            config.ui.include_tree = include_tree;
+        }
+        if let Some(budget) = self.replaced.token_budget {
+            config.output.token_budget = budget;
+        }
+    }
+
+    /// Name of the project config file, for display
+    #[must_use]
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// Returns whether the project sets the output format
+    #[must_use]
+    pub const fn sets_format(&self) -> bool {
+        self.replaced.output_format.is_some()
+    }
+
+    /// Returns whether the project sets whether the tree is included
+    #[must_use]
+    pub const fn sets_include_tree(&self) -> bool {
+        self.replaced.include_tree.is_some()
+    }
+
+    /// Returns whether the project sets the token budget
+    #[must_use]
+    pub const fn sets_token_budget(&self) -> bool {
+        self.replaced.token_budget.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_layer() {
+        let toml = ProjectConfig::parse(
+            ".fsprompt.toml",
+            r#"
+ignore_patterns = ["/fixtures"]
+format = "review"
+include_tree = false
+select = ["src/**/*.rs"]
+
+[token_budget]
+enabled = true
+max_tokens = 50000
+
+[templates.review]
+file = "{path}\n{content}\n"
+
+[templates.JSON]
+file = "{content}"
+"#,
+        )
+        .unwrap();
+        let json = ProjectConfig::parse(
+            ".fsprompt.json",
+            r#"{"ignore_patterns": ["/fixtures"], "format": "review", "include_tree": false,
+                "select": ["src/**/*.rs"], "token_budget": {"enabled": true, "max_tokens": 50000},
+                "templates": {"review": {"file": "{path}\n{content}\n"},
+                              "JSON": {"file": "{content}"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(toml, json);
+        assert!(ProjectConfig::parse(".fsprompt.toml", "formt = \"xml\"").is_err());
+
+        let mut config = AppConfig::default();
+        let user = config.clone();
+        let mut templates = Vec::new();
+        let (layer, warnings) = ProjectLayer::apply(
+            PathBuf::from(".fsprompt.toml"),
+            toml,
+            &mut config,
+            &mut templates,
+        );
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(templates.len(), 1);
+        assert_eq!(config.ui.output_format.name(), "review");
+        assert!(!config.ui.include_tree);
+        assert_eq!(config.output.token_budget.max_tokens, 50_000);
+        assert!(layer.sets_format() && layer.sets_include_tree() && layer.sets_token_budget());
+
+        layer.restore(&mut config);
+        assert_eq!(config.ui.output_format, user.ui.output_format);
+        assert_eq!(config.ui.include_tree, user.ui.include_tree);
+        assert_eq!(config.output.token_budget, user.output.token_budget);
+
+        let unknown = ProjectConfig {
+            format: Some("missing".to_string()),
+            ..ProjectConfig::default()
+        };
+        let (layer, warnings) =
+            ProjectLayer::apply(PathBuf::new(), unknown, &mut config, &mut templates);
+        assert_eq!(warnings.len(), 1);
+        assert!(!layer.sets_format());
+    }
+}
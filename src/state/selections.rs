@@ -0,0 +1,349 @@
+//! Named selection sets and the last selection, saved per root
+//!
+//! A selection set is a named group of files, such as "auth module + tests",
+//! saved for one root. The selection and expanded directories a root was
+//! left with are kept too, so reopening it picks up where it was. Paths are
+//! stored relative to the canonical root, and the selections of every root
+//! live in `selections.json` next to `config.json`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::hash::BuildHasher;
+use std::path::{Path, PathBuf};
+
+/// Root-relative paths of the files in a set
+pub type SelectionSet = BTreeSet<PathBuf>;
+
+/// How a saved set is combined with another set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    /// Files in either set
+    Union,
+    /// Files in both sets
+    Intersection,
+    /// Files in the first set but not the second
+    Difference,
+}
+
+impl SetOperation {
+    /// All operations, in menu order
+    pub const ALL: [Self; 3] = [Self::Union, Self::Intersection, Self::Difference];
+
+    /// Menu label, for a saved set combined with the current selection
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Union => "Add to selection",
+            Self::Intersection => "Intersect with selection",
+            Self::Difference => "Remove from selection",
+        }
+    }
+
+    /// Combines `left` with `right`
+    #[must_use]
+    pub fn apply(self, left: &SelectionSet, right: &SelectionSet) -> SelectionSet {
+        match self {
+            Self::Union => left.union(right).cloned().collect(),
+            Self::Intersection => left.intersection(right).cloned().collect(),
+            Self::Difference => left.difference(right).cloned().collect(),
+        }
+    }
+}
+
+/// Selection and expansion of a root when it was last left
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastSelection {
+    /// Selected files, including those selected as an outline
+    pub selected: SelectionSet,
+    /// Files selected as an outline
+    pub outline: SelectionSet,
+    /// Expanded directories
+    pub expanded: SelectionSet,
+}
+
+impl LastSelection {
+    /// Captures the state the tree reports, relative to `root`
+    #[must_use]
+    pub fn new<S: BuildHasher>(
+        root: &Path,
+        selected: &HashSet<String, S>,
+        outline: &HashSet<String, S>,
+        expanded: &HashSet<String, S>,
+    ) -> Self {
+        let mut expanded = relative_set(root, expanded);
+        // The root itself is always expanded
+        expanded.remove(Path::new(""));
+        Self {
+            selected: relative_set(root, selected),
+            outline: relative_set(root, outline),
+            expanded,
+        }
+    }
+
+    /// Returns whether nothing is selected or expanded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.selected.is_empty() && self.outline.is_empty() && self.expanded.is_empty()
+    }
+
+    /// Drops the entries that no longer exist under `root`
+    ///
+    /// Returns the selected files that were dropped.
+    pub fn prune(&mut self, root: &Path) -> Vec<PathBuf> {
+        let exists = |relative: &PathBuf| root.join(relative).exists();
+        let missing: Vec<PathBuf> = self
+            .selected
+            .iter()
+            .filter(|relative| !exists(relative))
+            .cloned()
+            .collect();
+        self.selected.retain(exists);
+        self.outline.retain(exists);
+        self.expanded.retain(exists);
+        missing
+    }
+}
+
+/// Selections saved for one root
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RootSelections {
+    /// Named sets
+    pub sets: BTreeMap<String, SelectionSet>,
+    /// Selection the root was last left with
+    pub last: Option<LastSelection>,
+}
+
+/// Saved selections of every root
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectionStore {
+    /// Selections keyed by canonical root
+    roots: BTreeMap<PathBuf, RootSelections>,
+}
+
+impl SelectionStore {
+    /// Reads the store from `path`, or an empty store if the file does not exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is invalid
+    pub fn load(path: &Path) -> Result<Self, String> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read saved selections: {e}"))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid saved selections: {e}"))
+    }
+
+    /// Writes the store to `path`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing the file fails
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to save selections: {e}"))?;
+        std::fs::write(path, json).map_err(|e| format!("Failed to save selections: {e}"))
+    }
+
+    /// Named sets saved for `root`, ordered by name
+    pub fn sets(&self, root: &Path) -> impl Iterator<Item = (&String, &SelectionSet)> {
+        self.roots
+            .get(root)
+            .into_iter()
+            .flat_map(|selections| selections.sets.iter())
+    }
+
+    /// The set saved for `root` under `name`
+    #[must_use]
+    pub fn get(&self, root: &Path, name: &str) -> Option<&SelectionSet> {
+        self.roots.get(root)?.sets.get(name)
+    }
+
+    /// Saves `set` for `root` under `name`, replacing any set of that name
+    pub fn insert(&mut self, root: &Path, name: &str, set: SelectionSet) {
+        self.roots
+            .entry(root.to_path_buf())
+            .or_default()
+            .sets
+            .insert(name.to_string(), set);
+    }
+
+    /// Deletes the set saved for `root` under `name`, returning whether it existed
+    pub fn remove(&mut self, root: &Path, name: &str) -> bool {
+        let Some(selections) = self.roots.get_mut(root) else {
+            return false;
+        };
+        let removed = selections.sets.remove(name).is_some();
+        if selections == &RootSelections::default() {
+            self.roots.remove(root);
+        }
+        removed
+    }
+
+    /// Selection `root` was last left with
+    #[must_use]
+    pub fn last(&self, root: &Path) -> Option<&LastSelection> {
+        self.roots.get(root)?.last.as_ref()
+    }
+
+    /// Remembers the selection `root` is left with; an empty one is forgotten
+    pub fn set_last(&mut self, root: &Path, last: LastSelection) {
+        if last.is_empty() {
+            if let Some(selections) = self.roots.get_mut(root) {
+                selections.last = None;
+                if selections == &RootSelections::default() {
+                    self.roots.remove(root);
+                }
+            }
+        } else {
+            self.roots.entry(root.to_path_buf()).or_default().last = Some(last);
+        }
+    }
+
+    /// Union of the sets saved for `root` under `names`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first set that does not exist
+    pub fn resolve(&self, root: &Path, names: &[String]) -> Result<SelectionSet, String> {
+        let mut files = SelectionSet::new();
+        for name in names {
+            let set = self.get(root, name).ok_or_else(|| {
+                let saved: Vec<&str> = self.sets(root).map(|(name, _)| name.as_str()).collect();
+                if saved.is_empty() {
+                    format!("Unknown selection set: {name} (none saved for this root)")
+                } else {
+                    format!(
+                        "Unknown selection set: {name} (saved: {})",
+                        saved.join(", ")
+                    )
+                }
+            })?;
+            files.extend(set.iter().cloned());
+        }
+        Ok(files)
+    }
+}
+
+/// Converts selected file paths, as the tree reports them, to a set relative to `root`
+///
+/// Paths outside the root are left out.
+#[must_use]
+pub fn relative_set<S: BuildHasher>(root: &Path, files: &HashSet<String, S>) -> SelectionSet {
+    files
+        .iter()
+        .filter_map(|file| Path::new(file).strip_prefix(root).ok())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Converts a set back to the absolute paths the tree works with
+#[must_use]
+pub fn absolute_paths(root: &Path, set: &SelectionSet) -> HashSet<String> {
+    set.iter()
+        .map(|relative| root.join(relative).to_string_lossy().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn set(paths: &[&str]) -> SelectionSet {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_selection_sets() {
+        let root = Path::new("/project");
+        let auth = set(&["src/auth.rs", "tests/auth.rs"]);
+        let tests = set(&["tests/auth.rs", "tests/api.rs"]);
+        assert_eq!(
+            SetOperation::Union.apply(&auth, &tests),
+            set(&["src/auth.rs", "tests/api.rs", "tests/auth.rs"])
+        );
+        assert_eq!(
+            SetOperation::Intersection.apply(&auth, &tests),
+            set(&["tests/auth.rs"])
+        );
+        assert_eq!(
+            SetOperation::Difference.apply(&auth, &tests),
+            set(&["src/auth.rs"])
+        );
+
+        let selected: HashSet<String> = ["/project/src/auth.rs", "/elsewhere/a.rs"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        let relative = relative_set(root, &selected);
+        assert_eq!(relative, set(&["src/auth.rs"]));
+        assert!(absolute_paths(root, &relative).contains("/project/src/auth.rs"));
+
+        let mut store = SelectionStore::default();
+        store.insert(root, "auth", auth.clone());
+        store.insert(root, "tests", tests);
+        store.insert(Path::new("/other"), "auth", SelectionSet::new());
+        let names: Vec<&String> = store.sets(root).map(|(name, _)| name).collect();
+        assert_eq!(names, ["auth", "tests"]);
+        assert_eq!(
+            store
+                .resolve(root, &["auth".to_string(), "tests".to_string()])
+                .unwrap()
+                .len(),
+            3
+        );
+        assert!(store.resolve(root, &["api".to_string()]).is_err());
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("selections.json");
+        assert_eq!(
+            SelectionStore::load(&path).unwrap(),
+            SelectionStore::default()
+        );
+        store.save(&path).unwrap();
+        let mut loaded = SelectionStore::load(&path).unwrap();
+        assert_eq!(loaded, store);
+        assert_eq!(loaded.get(root, "auth"), Some(&auth));
+
+        assert!(loaded.remove(Path::new("/other"), "auth"));
+        assert!(!loaded.remove(Path::new("/other"), "auth"));
+        assert_eq!(loaded.sets(Path::new("/other")).count(), 0);
+    }
+
+    #[test]
+    fn test_last_selection() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "").unwrap();
+        let absolute = |paths: &[&str]| -> HashSet<String> {
+            paths
+                .iter()
+                .map(|path| root.join(path).to_string_lossy().to_string())
+                .collect()
+        };
+
+        let mut last = LastSelection::new(
+            root,
+            &absolute(&["src/main.rs", "src/gone.rs"]),
+            &absolute(&["src/gone.rs"]),
+            &absolute(&["", "src", "old"]),
+        );
+        assert_eq!(last.expanded, set(&["old", "src"]));
+        assert_eq!(last.prune(root), vec![PathBuf::from("src/gone.rs")]);
+        assert_eq!(last.selected, set(&["src/main.rs"]));
+        assert!(last.outline.is_empty());
+        assert_eq!(last.expanded, set(&["src"]));
+
+        let mut store = SelectionStore::default();
+        store.set_last(root, last.clone());
+        assert_eq!(store.last(root), Some(&last));
+        store.set_last(root, LastSelection::default());
+        assert_eq!(store, SelectionStore::default());
+    }
+}
@@ -1,6 +1,15 @@
 //! UI rendering logic for the main application
 
 use crate::app::FsPromptApp;
+use crate::core::budget::PackingStrategy;
+use crate::core::encoding::FallbackEncoding;
+use crate::core::line_numbers::LineSeparator;
+use crate::core::secrets;
+use crate::core::size_cap::OversizeAction;
+use crate::core::split::SplitUnit;
+use crate::core::tokenizer::TokenizerModel;
+use crate::core::types::CanonicalPath;
+use crate::state::{ProjectLayer, SetOperation};
 use crate::ui::{
     components::{Button, ButtonSize, ButtonVariant},
     footer::Footer,
@@ -9,12 +18,20 @@ use crate::ui::{
     theme::TextEmphasis,
     Theme as UiTheme,
 };
+use crate::utils::git::GitSelection;
+use crate::utils::rust_deps::RelatedDirection;
+use crate::workers::tokens::TokenCommand;
 use eframe::egui;
 
 impl FsPromptApp {
     /// Shows just the action bar (for global positioning) - single row layout
     pub fn show_action_bar(&mut self, ui: &mut egui::Ui) {
         let mut generate_requested = false;
+        let format_set_by = self
+            .project
+            .as_ref()
+            .is_some_and(ProjectLayer::sets_format)
+            .then(|| self.project_hover_text());
 
         Footer::new(
             &mut self.state,
@@ -24,6 +41,7 @@ impl FsPromptApp {
             &self.current_progress,
         )
         .on_generate(|| generate_requested = true)
+        .format_set_by(format_set_by)
         .show(ui);
 
         if generate_requested {
@@ -62,8 +80,15 @@ impl FsPromptApp {
                         .tooltip("Reload directory contents");
 
                     if refresh_button.show(ui, &mut self.icon_manager).clicked() {
-                        if let Some(root) = &self.state.root {
-                            self.tree.set_root(root.clone());
+                        if let Some(root) = self.state.root.clone() {
+                            self.remember_selection();
+                            self.load_project_config(&root);
+                            self.tree
+                                .set_ignore_patterns(&self.ignore_patterns().join(","));
+                            self.tree.set_root(root);
+                            self.refresh_git_status();
+                            self.restore_last_selection();
+                            self.refresh_token_estimate();
                             self.files_changed = false;
                             self.toast_manager.success("Directory refreshed");
                         }
@@ -71,6 +96,14 @@ impl FsPromptApp {
                 });
             }
 
+            if self.tree.has_git_changes() {
+                self.show_git_actions(ui);
+            }
+            self.show_related_actions(ui);
+            self.show_selection_sets(ui);
+            self.show_selection_query(ui);
+            self.show_content_search(ui);
+
             // Error display
             if let Some(error) = &self.error_message {
                 ui.horizontal(|ui| {
@@ -88,14 +121,328 @@ impl FsPromptApp {
 
             // Check if selection changed and record state
             let snapshot_after = self.capture_snapshot();
-            if snapshot_before.selected_files != snapshot_after.selected_files {
+            if snapshot_before.selected_files != snapshot_after.selected_files
+                || snapshot_before.outline_files != snapshot_after.outline_files
+            {
                 self.record_state();
                 // Update real-time token count when selection changes
-                self.state.output.estimated_tokens = Some(self.estimate_tokens_for_selection());
+                self.refresh_token_estimate();
             }
         });
     }
 
+    /// Shows the menu that selects files changed in git
+    fn show_git_actions(&mut self, ui: &mut egui::Ui) {
+        let mut selection = None;
+        ui.horizontal(|ui| {
+            ui.add_space(UiTheme::SPACING_SM);
+            ui.menu_button("Select changed", |ui| {
+                for (label, choice) in [
+                    ("Modified files", GitSelection::Modified),
+                    ("Staged files", GitSelection::Staged),
+                    ("Untracked files", GitSelection::Untracked),
+                ] {
+                    if ui.button(label).clicked() {
+                        selection = Some(choice);
+                        ui.close_menu();
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.git_base_ref)
+                            .hint_text("main")
+                            .desired_width(100.0),
+                    );
+                    let base = self.git_base_ref.trim();
+                    if ui
+                        .add_enabled(!base.is_empty(), egui::Button::new("Diff vs base"))
+                        .on_hover_text("Files changed on this branch since it left the base ref")
+                        .clicked()
+                    {
+                        selection = Some(GitSelection::BranchDiff(base.to_string()));
+                        ui.close_menu();
+                    }
+                });
+            })
+            .response
+            .on_hover_text("Select exactly the files git reports as changed");
+        });
+        if let Some(selection) = selection {
+            self.select_git_files(&selection);
+        }
+    }
+
+    /// Shows the menu that adds Rust files related to the checked ones
+    fn show_related_actions(&mut self, ui: &mut egui::Ui) {
+        let mut direction = None;
+        ui.horizontal(|ui| {
+            ui.add_space(UiTheme::SPACING_SM);
+            ui.menu_button("Select related", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Depth");
+                    ui.add(egui::DragValue::new(&mut self.related_depth).range(1..=10));
+                });
+                if ui
+                    .button("Dependencies")
+                    .on_hover_text(
+                        "Modules the checked files declare with mod or name with crate::",
+                    )
+                    .clicked()
+                {
+                    direction = Some(RelatedDirection::Dependencies);
+                    ui.close_menu();
+                }
+                if ui
+                    .button("Dependents")
+                    .on_hover_text("Files of the crate that declare or name the checked files")
+                    .clicked()
+                {
+                    direction = Some(RelatedDirection::Dependents);
+                    ui.close_menu();
+                }
+            })
+            .response
+            .on_hover_text("Add the Rust modules related to the checked .rs files");
+        });
+        if let Some(direction) = direction {
+            self.select_related_files(direction);
+        }
+    }
+
+    /// Shows the selection sets saved for the root, with a field to save the checked files
+    fn show_selection_sets(&mut self, ui: &mut egui::Ui) {
+        let Some(root) = &self.state.root else {
+            return;
+        };
+        let sets: Vec<(String, usize)> = self
+            .selections
+            .sets(root.as_path())
+            .map(|(name, set)| (name.clone(), set.len()))
+            .collect();
+        let mut apply = None;
+        let mut delete = None;
+        let mut save = false;
+
+        ui.horizontal(|ui| {
+            ui.add_space(UiTheme::SPACING_SM);
+            ui.vertical(|ui| {
+                egui::CollapsingHeader::new(format!("Selection sets ({})", sets.len()))
+                    .id_salt("selection_sets")
+                    .show(ui, |ui| {
+                        for (name, count) in &sets {
+                            ui.horizontal(|ui| {
+                                if ui
+                                    .link(name)
+                                    .on_hover_text("Select exactly the files in this set")
+                                    .clicked()
+                                {
+                                    apply = Some((name.clone(), None));
+                                }
+                                ui.weak(format!("{count} file(s)"));
+                                ui.menu_button("⋯", |ui| {
+                                    for operation in SetOperation::ALL {
+                                        if ui.button(operation.label()).clicked() {
+                                            apply = Some((name.clone(), Some(operation)));
+                                            ui.close_menu();
+                                        }
+                                    }
+                                    ui.separator();
+                                    if ui.button("Delete").clicked() {
+                                        delete = Some(name.clone());
+                                        ui.close_menu();
+                                    }
+                                });
+                            });
+                        }
+
+                        ui.horizontal(|ui| {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut self.selection_set_name)
+                                    .hint_text("Set name")
+                                    .desired_width(150.0),
+                            );
+                            let named = !self.selection_set_name.trim().is_empty();
+                            save = ui
+                                .add_enabled(named, egui::Button::new("Save selection"))
+                                .on_hover_text(
+                                    "Save the checked files under this name, \
+                                     replacing a set of the same name",
+                                )
+                                .clicked()
+                                || (named
+                                    && response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter)));
+                        });
+                    });
+            });
+        });
+
+        if save {
+            let name = self.selection_set_name.trim().to_string();
+            self.save_selection_set(&name);
+            self.selection_set_name.clear();
+        }
+        if let Some((name, operation)) = apply {
+            self.apply_selection_set(&name, operation);
+        }
+        if let Some(name) = delete {
+            self.delete_selection_set(&name);
+        }
+    }
+
+    /// Shows the query box that checks or unchecks every file matching globs or regexes
+    fn show_selection_query(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut apply = None;
+
+        ui.horizontal(|ui| {
+            ui.add_space(UiTheme::SPACING_SM);
+            ui.vertical(|ui| {
+                egui::CollapsingHeader::new("Select by pattern")
+                    .id_salt("selection_query")
+                    .show(ui, |ui| {
+                        changed = ui
+                            .add(
+                                egui::TextEdit::singleline(&mut self.query.input)
+                                    .hint_text("api/**/*.proto !**/tests/**")
+                                    .desired_width(f32::INFINITY),
+                            )
+                            .on_hover_text(
+                                "Globs or re:regexes matched against root-relative paths, \
+                                 separated by spaces; a leading ! excludes matches",
+                            )
+                            .changed();
+
+                        ui.horizontal(|ui| {
+                            if let Some(error) = &self.query.error {
+                                ui.colored_label(UiTheme::ERROR, error);
+                            } else if self.query.searching {
+                                ui.spinner();
+                                ui.weak("Matching…");
+                            } else if let Some(matches) = &self.query.matches {
+                                ui.label(format!("{} matching file(s)", matches.len()));
+                                let any = !matches.is_empty();
+                                if ui.add_enabled(any, egui::Button::new("Select")).clicked() {
+                                    apply = Some(true);
+                                }
+                                if ui.add_enabled(any, egui::Button::new("Deselect")).clicked() {
+                                    apply = Some(false);
+                                }
+                            }
+                        });
+                    });
+            });
+        });
+
+        if changed {
+            self.update_query_preview();
+        }
+        if let Some(select) = apply {
+            self.apply_query(select);
+        }
+    }
+
+    /// Shows the content search with its matching files and preview lines
+    fn show_content_search(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut select = None;
+
+        ui.horizontal(|ui| {
+            ui.add_space(UiTheme::SPACING_SM);
+            ui.vertical(|ui| {
+                egui::CollapsingHeader::new("Search contents")
+                    .id_salt("content_search")
+                    .show(ui, |ui| {
+                        let search = &mut self.content_search;
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::singleline(&mut search.input)
+                                    .hint_text("Text in files")
+                                    .desired_width(f32::INFINITY),
+                            )
+                            .changed();
+                        ui.horizontal(|ui| {
+                            changed |= ui.checkbox(&mut search.regex, "Regex").changed();
+                            changed |= ui
+                                .checkbox(&mut search.case_sensitive, "Match case")
+                                .changed();
+                        });
+
+                        if let Some(error) = &search.error {
+                            ui.colored_label(UiTheme::ERROR, error);
+                        } else if search.searching {
+                            ui.horizontal(|ui| {
+                                ui.spinner();
+                                ui.weak("Searching…");
+                            });
+                        } else if let Some(matches) = &search.matches {
+                            let hits: usize = matches.iter().map(|m| m.hits).sum();
+                            ui.label(format!(
+                                "{hits} match(es) in {} file(s)",
+                                matches.len()
+                            ));
+                            ui.horizontal_wrapped(|ui| {
+                                let any = !matches.is_empty();
+                                if ui
+                                    .add_enabled(any, egui::Button::new("Select matches"))
+                                    .clicked()
+                                {
+                                    select = Some(false);
+                                }
+                                if ui
+                                    .add_enabled(any, egui::Button::new("+ siblings"))
+                                    .on_hover_text(
+                                        "Select the matches and the other files in their directories",
+                                    )
+                                    .clicked()
+                                {
+                                    select = Some(true);
+                                }
+                            });
+                            self.show_content_matches(ui);
+                        }
+                    });
+            });
+        });
+
+        if changed {
+            self.update_content_search();
+        }
+        if let Some(with_siblings) = select {
+            self.select_content_matches(with_siblings);
+        }
+    }
+
+    /// Lists the files matching the content search with their hit counts and preview lines
+    fn show_content_matches(&self, ui: &mut egui::Ui) {
+        let Some(matches) = &self.content_search.matches else {
+            return;
+        };
+        let root = self.state.root.as_ref().map(CanonicalPath::as_path);
+        egui::ScrollArea::vertical()
+            .id_salt("content_matches")
+            .max_height(240.0)
+            .show(ui, |ui| {
+                for found in matches {
+                    let path = root
+                        .and_then(|root| found.path.strip_prefix(root).ok())
+                        .unwrap_or(&found.path);
+                    ui.horizontal(|ui| {
+                        ui.strong(path.display().to_string());
+                        ui.weak(format!("{} hit(s)", found.hits));
+                    });
+                    for line in &found.preview {
+                        ui.label(
+                            egui::RichText::new(format!("{:>5}  {}", line.number, line.text))
+                                .monospace()
+                                .small(),
+                        );
+                    }
+                }
+            });
+    }
+
     /// Renders the complete files panel (for tab/narrow view)
     pub fn show_files_panel(&mut self, ui: &mut egui::Ui) {
         // Fixed bottom action bar
@@ -121,28 +468,159 @@ impl FsPromptApp {
         OutputPanel::new(self).show(ui, ctx);
     }
 
-    /// Estimates token count for current selection
-    pub fn estimate_tokens_for_selection(&self) -> usize {
-        let selected_files = self.tree.get_selected_files();
-        // Rough estimate: 1 token per 4 characters
-        selected_files
-            .iter()
-            .filter_map(|path| std::fs::metadata(path).ok())
-            .map(|metadata| (metadata.len() / 4) as usize)
-            .sum()
+    /// Starts recounting the token estimate for the current selection in the background
+    ///
+    /// Uses the configured tokenizer; per-file counts are cached until the
+    /// file changes on disk.
+    pub fn refresh_token_estimate(&mut self) {
+        let files: Vec<std::path::PathBuf> = self
+            .tree
+            .get_selected_files()
+            .into_iter()
+            .map(std::path::PathBuf::from)
+            .collect();
+        self.token_request += 1;
+        let command = TokenCommand::Count {
+            id: self.token_request,
+            cache: self
+                .state
+                .output
+                .token_cache
+                .subset(files.iter().map(std::path::PathBuf::as_path)),
+            files,
+            model: self.state.config.output.tokenizer,
+            encoding: self.state.config.output.encoding,
+        };
+        self.state.output.counting_tokens = self.tokens.send_command(command).is_ok();
     }
 
     /// Applies current ignore patterns to the tree
     fn apply_patterns(&mut self) {
         self.tree
-            .set_ignore_patterns(&self.state.config.ignore_patterns.join(","));
+            .set_ignore_patterns(&self.ignore_patterns().join(","));
         self.save_config();
         self.saved_ignore_patterns
             .clone_from(&self.state.config.ignore_patterns);
         if let Some(root) = &self.state.root {
             self.tree.set_root(root.clone());
         }
-        self.toast_manager.success("Patterns applied");
+        self.refresh_token_estimate();
+        self.update_query_preview();
+        self.update_content_search();
+        let invalid = self.tree.invalid_patterns();
+        if invalid.is_empty() {
+            self.toast_manager.success("Patterns applied");
+        } else {
+            let message = format!("Skipped invalid patterns: {}", invalid.join("; "));
+            self.toast_manager.warning(message);
+        }
+    }
+
+    /// Shows the checkbox that turns ignore file support on and off
+    fn show_ignore_file_toggle(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .checkbox(
+                &mut self.state.config.respect_ignore_files,
+                "Respect .gitignore and other ignore files",
+            )
+            .on_hover_text(
+                "Hide files listed in .gitignore, .ignore, .fspromptignore \
+                 and .git/info/exclude",
+            )
+            .changed()
+        {
+            self.tree
+                .set_respect_ignore_files(self.state.config.respect_ignore_files);
+            self.save_config();
+            self.refresh_token_estimate();
+            self.update_query_preview();
+            self.update_content_search();
+        }
+    }
+
+    /// Shows the tree and startup checkboxes
+    fn show_general_settings(&mut self, ui: &mut egui::Ui) {
+        // Include tree checkbox
+        let project_tree = self
+            .project
+            .as_ref()
+            .is_some_and(ProjectLayer::sets_include_tree);
+        ui.add_enabled(
+            !project_tree,
+            egui::Checkbox::new(
+                &mut self.state.config.ui.include_tree,
+                "Include directory tree in output",
+            ),
+        )
+        .on_disabled_hover_text(self.project_hover_text());
+        ui.checkbox(
+            &mut self.state.config.ui.reopen_last_directory,
+            "Reopen the last directory on startup",
+        );
+    }
+
+    /// Shows the ignore patterns with remove buttons, followed by those of the project
+    fn show_ignore_pattern_list(&mut self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .max_height(150.0)
+            .show(ui, |ui| {
+                let mut patterns_to_remove = Vec::new();
+                for (idx, pattern) in self.state.config.ignore_patterns.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(pattern);
+
+                        let remove_button = Button::icon_only(IconType::Close)
+                            .size(ButtonSize::Small)
+                            .tooltip("Remove pattern");
+
+                        if remove_button.show(ui, &mut self.icon_manager).clicked() {
+                            patterns_to_remove.push(idx);
+                        }
+                    });
+                }
+                for &idx in patterns_to_remove.iter().rev() {
+                    self.state.config.ignore_patterns.remove(idx);
+                }
+
+                // Project patterns can only be changed in the project file
+                if let Some(layer) = &self.project {
+                    for pattern in &layer.config.ignore_patterns {
+                        ui.horizontal(|ui| {
+                            ui.label(pattern);
+                            ui.label(egui::RichText::new("project").small().weak())
+                                .on_hover_text(self.project_hover_text());
+                        });
+                    }
+                }
+            });
+    }
+
+    /// Shows the field and button that add an ignore pattern
+    fn show_add_pattern(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.new_pattern_input)
+                    .hint_text("Add pattern (e.g., *.log)")
+                    .desired_width(250.0),
+            );
+
+            let add_button = Button::new("+")
+                .variant(ButtonVariant::Primary)
+                .size(ButtonSize::Small)
+                .tooltip("Add pattern");
+
+            if (add_button.show(ui, &mut self.icon_manager).clicked()
+                || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))))
+                && !self.new_pattern_input.trim().is_empty()
+            {
+                self.state
+                    .config
+                    .ignore_patterns
+                    .push(self.new_pattern_input.trim().to_string());
+                self.new_pattern_input.clear();
+                self.apply_patterns();
+            }
+        });
     }
 
     /// Shows the settings popover window
@@ -150,6 +628,8 @@ impl FsPromptApp {
         let mut show_settings = self.state.config.ui.show_settings;
         let dark_mode = ctx.style().visuals.dark_mode;
         let tokens = UiTheme::design_tokens(dark_mode);
+        let output_before = self.state.config.output.clone();
+        let ui_before = self.state.config.ui.clone();
 
         egui::Window::new("Settings")
             .id(egui::Id::new("settings_popover"))
@@ -172,11 +652,9 @@ impl FsPromptApp {
             )
             .show(ctx, |ui| {
                 ui.vertical(|ui| {
-                    // Include tree checkbox
-                    ui.checkbox(
-                        &mut self.state.config.ui.include_tree,
-                        "Include directory tree in output",
-                    );
+                    self.show_project_settings(ui);
+                    self.show_general_settings(ui);
+                    self.show_output_settings(ui);
 
                     ui.separator();
 
@@ -184,59 +662,14 @@ impl FsPromptApp {
                     ui.label(egui::RichText::new("Ignore Patterns").heading());
                     ui.add_space(UiTheme::SPACING_SM);
 
-                    // Pattern list
-                    egui::ScrollArea::vertical()
-                        .max_height(150.0)
-                        .show(ui, |ui| {
-                            let mut patterns_to_remove = Vec::new();
-                            for (idx, pattern) in
-                                self.state.config.ignore_patterns.iter().enumerate()
-                            {
-                                ui.horizontal(|ui| {
-                                    ui.label(pattern);
-
-                                    let remove_button = Button::icon_only(IconType::Close)
-                                        .size(ButtonSize::Small)
-                                        .tooltip("Remove pattern");
-
-                                    if remove_button.show(ui, &mut self.icon_manager).clicked() {
-                                        patterns_to_remove.push(idx);
-                                    }
-                                });
-                            }
-                            for &idx in patterns_to_remove.iter().rev() {
-                                self.state.config.ignore_patterns.remove(idx);
-                            }
-                        });
-
+                    self.show_ignore_file_toggle(ui);
                     ui.add_space(UiTheme::SPACING_SM);
 
-                    // Add pattern input
-                    ui.horizontal(|ui| {
-                        let response = ui.add(
-                            egui::TextEdit::singleline(&mut self.new_pattern_input)
-                                .hint_text("Add pattern (e.g., *.log)")
-                                .desired_width(250.0),
-                        );
+                    self.show_ignore_pattern_list(ui);
 
-                        let add_button = Button::new("+")
-                            .variant(ButtonVariant::Primary)
-                            .size(ButtonSize::Small)
-                            .tooltip("Add pattern");
+                    ui.add_space(UiTheme::SPACING_SM);
 
-                        if (add_button.show(ui, &mut self.icon_manager).clicked()
-                            || (response.lost_focus()
-                                && ui.input(|i| i.key_pressed(egui::Key::Enter))))
-                            && !self.new_pattern_input.trim().is_empty()
-                        {
-                            self.state
-                                .config
-                                .ignore_patterns
-                                .push(self.new_pattern_input.trim().to_string());
-                            self.new_pattern_input.clear();
-                            self.apply_patterns();
-                        }
-                    });
+                    self.show_add_pattern(ui);
 
                     ui.add_space(UiTheme::SPACING_SM);
 
@@ -285,6 +718,15 @@ impl FsPromptApp {
                 });
             });
 
+        if self.state.config.output != output_before || self.state.config.ui != ui_before {
+            self.save_config();
+            if self.state.config.output.tokenizer != output_before.tokenizer
+                || self.state.config.output.encoding != output_before.encoding
+            {
+                self.refresh_token_estimate();
+            }
+        }
+
         // Update settings state based on window visibility
         self.state.config.ui.show_settings = show_settings;
 
@@ -294,6 +736,376 @@ impl FsPromptApp {
         }
     }
 
+    /// Hover text for settings that the project config controls
+    fn project_hover_text(&self) -> String {
+        self.project.as_ref().map_or_else(String::new, |layer| {
+            format!("Set by {} in the opened folder", layer.file_name())
+        })
+    }
+
+    /// Lists the values the project config sets, if one is applied
+    fn show_project_settings(&self, ui: &mut egui::Ui) {
+        let Some(layer) = &self.project else {
+            return;
+        };
+        let project = &layer.config;
+        let mut values = Vec::new();
+        if layer.sets_format() {
+            values.push(format!(
+                "format {}",
+                self.state.config.ui.output_format.name()
+            ));
+        }
+        if let Some(include_tree) = project.include_tree {
+            values.push(format!("tree {}", if include_tree { "on" } else { "off" }));
+        }
+        if layer.sets_token_budget() {
+            values.push(format!(
+                "budget {} tokens",
+                self.state.config.output.token_budget.max_tokens
+            ));
+        }
+        for (count, what) in [
+            (project.ignore_patterns.len(), "ignore pattern"),
+            (project.select.len(), "selection glob"),
+            (project.templates.len(), "template"),
+        ] {
+            if count > 0 {
+                values.push(format!(
+                    "{count} {what}{}",
+                    if count == 1 { "" } else { "s" }
+                ));
+            }
+        }
+
+        ui.label(
+            egui::RichText::new(format!("📁 Project settings from {}", layer.file_name())).strong(),
+        )
+        .on_hover_text(
+            "Values from the project file replace your own while this folder is open \
+                 and are not saved to your settings",
+        );
+        if !values.is_empty() {
+            ui.label(egui::RichText::new(values.join(" · ")).small().weak());
+        }
+        ui.separator();
+    }
+
+    /// Shows the output generation settings
+    fn show_output_settings(&mut self, ui: &mut egui::Ui) {
+        // Tokenizer used for token counts
+        ui.horizontal(|ui| {
+            ui.label("Token counts:");
+            let tokenizer = &mut self.state.config.output.tokenizer;
+            egui::ComboBox::from_id_salt("tokenizer_model")
+                .selected_text(tokenizer.label())
+                .show_ui(ui, |ui| {
+                    for model in TokenizerModel::ALL {
+                        ui.selectable_value(tokenizer, model, model.label());
+                    }
+                });
+        });
+
+        // Token budget packing
+        let project_budget = self
+            .project
+            .as_ref()
+            .is_some_and(ProjectLayer::sets_token_budget);
+        let project_hover = self.project_hover_text();
+        let budget = &mut self.state.config.output.token_budget;
+        ui.add_enabled(
+            !project_budget,
+            egui::Checkbox::new(&mut budget.enabled, "Fit selection into a token budget"),
+        )
+        .on_disabled_hover_text(&project_hover);
+        ui.add_enabled_ui(budget.enabled && !project_budget, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Budget:");
+                ui.add(
+                    egui::DragValue::new(&mut budget.max_tokens)
+                        .range(1_000..=2_000_000)
+                        .speed(1_000)
+                        .suffix(" tokens"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Give up:");
+                egui::ComboBox::from_id_salt("budget_strategy")
+                    .selected_text(budget.strategy.label())
+                    .show_ui(ui, |ui| {
+                        for strategy in PackingStrategy::ALL {
+                            ui.selectable_value(&mut budget.strategy, strategy, strategy.label());
+                        }
+                    });
+            });
+            ui.checkbox(
+                &mut budget.truncate,
+                "Truncate the last file instead of dropping it",
+            );
+            ui.label(
+                egui::RichText::new("Right-click a file in the tree to pin it")
+                    .small()
+                    .weak(),
+            );
+        });
+
+        // Splitting into numbered parts
+        let split = &mut self.state.config.output.split;
+        ui.checkbox(&mut split.enabled, "Split output into parts");
+        ui.add_enabled_ui(split.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("At most:");
+                ui.add(
+                    egui::DragValue::new(&mut split.limit)
+                        .range(1_000..=10_000_000)
+                        .speed(1_000),
+                );
+                egui::ComboBox::from_id_salt("split_unit")
+                    .selected_text(split.unit.name())
+                    .show_ui(ui, |ui| {
+                        for unit in SplitUnit::ALL {
+                            ui.selectable_value(&mut split.unit, unit, unit.name());
+                        }
+                    });
+                ui.label("per part");
+            });
+        });
+
+        // Git branch, commits and diff section
+        let git_context = &mut self.state.config.output.git_context;
+        ui.checkbox(&mut git_context.enabled, "Include git context")
+            .on_hover_text("Adds the branch, recent commits and the diff of the selected files");
+        ui.add_enabled_ui(git_context.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Commits:");
+                ui.add(egui::DragValue::new(&mut git_context.commit_count).range(0..=100));
+                ui.label("Diff base:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut git_context.diff_base)
+                        .hint_text("working tree")
+                        .desired_width(100.0),
+                )
+                .on_hover_text("Diffs base...HEAD, from where the branch left the base; leave empty to diff uncommitted changes");
+            });
+        });
+
+        self.show_file_settings(ui);
+    }
+
+    /// Shows the settings for how individual files are read
+    fn show_file_settings(&mut self, ui: &mut egui::Ui) {
+        // Per-file size cap
+        let cap = &mut self.state.config.output.size_cap;
+        ui.checkbox(&mut cap.enabled, "Cap the size of each file");
+        ui.add_enabled_ui(cap.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("At most:");
+                ui.add(
+                    egui::DragValue::new(&mut cap.max_size)
+                        .range(100..=10_000_000)
+                        .speed(100),
+                );
+                egui::ComboBox::from_id_salt("size_cap_unit")
+                    .selected_text(cap.unit.name())
+                    .show_ui(ui, |ui| {
+                        for unit in SplitUnit::ALL {
+                            ui.selectable_value(&mut cap.unit, unit, unit.name());
+                        }
+                    });
+                ui.label("per file");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Larger files:");
+                egui::ComboBox::from_id_salt("oversize_action")
+                    .selected_text(cap.action.label())
+                    .show_ui(ui, |ui| {
+                        for action in OversizeAction::ALL {
+                            ui.selectable_value(&mut cap.action, action, action.label());
+                        }
+                    });
+            });
+            ui.add_enabled_ui(cap.action == OversizeAction::Truncate, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Keep first");
+                    ui.add(egui::DragValue::new(&mut cap.head_lines).range(0..=100_000));
+                    ui.label("and last");
+                    ui.add(egui::DragValue::new(&mut cap.tail_lines).range(0..=100_000));
+                    ui.label("lines");
+                });
+            });
+        });
+
+        // Binary files are skipped unless listed as placeholders
+        ui.checkbox(
+            &mut self.state.config.output.binary_placeholders,
+            "List binary files with size and type",
+        );
+
+        // Decoding of non-UTF-8 files
+        let encoding = &mut self.state.config.output.encoding;
+        ui.horizontal(|ui| {
+            ui.label("Non-UTF-8 files:");
+            egui::ComboBox::from_id_salt("fallback_encoding")
+                .selected_text(encoding.fallback.label())
+                .show_ui(ui, |ui| {
+                    for fallback in FallbackEncoding::ALL {
+                        ui.selectable_value(&mut encoding.fallback, fallback, fallback.label());
+                    }
+                });
+        });
+        ui.checkbox(
+            &mut encoding.lossy,
+            "Replace undecodable bytes instead of failing",
+        );
+        ui.checkbox(
+            &mut encoding.normalize_line_endings,
+            "Convert CRLF line endings to LF",
+        );
+
+        self.show_secret_settings(ui);
+
+        // Content transforms
+        let transforms = &mut self.state.config.output.transforms;
+        ui.checkbox(&mut transforms.strip_comments, "Strip comments")
+            .on_hover_text("Removes line and block comments from source files");
+        ui.checkbox(&mut transforms.collapse_blank_lines, "Collapse blank lines");
+
+        // Line numbers in file contents
+        let line_numbers = &mut self.state.config.output.line_numbers;
+        ui.checkbox(&mut line_numbers.enabled, "Number content lines");
+        ui.add_enabled_ui(line_numbers.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Width:");
+                ui.add(egui::DragValue::new(&mut line_numbers.width).range(1..=10));
+                ui.label("Separator:");
+                egui::ComboBox::from_id_salt("line_number_separator")
+                    .selected_text(line_numbers.separator.label())
+                    .show_ui(ui, |ui| {
+                        for separator in LineSeparator::ALL {
+                            ui.selectable_value(
+                                &mut line_numbers.separator,
+                                separator,
+                                separator.label(),
+                            );
+                        }
+                    });
+            });
+        });
+    }
+
+    /// Shows the secret redaction settings
+    fn show_secret_settings(&mut self, ui: &mut egui::Ui) {
+        let secrets = &mut self.state.config.output.secrets;
+        ui.checkbox(&mut secrets.enabled, "Redact secrets")
+            .on_hover_text(
+                "Replaces API keys, tokens, private keys and passwords with [REDACTED:kind]",
+            );
+        ui.add_enabled_ui(secrets.enabled, |ui| {
+            let mut pattern_to_remove = None;
+            for (idx, pattern) in secrets.custom_patterns.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.monospace(pattern);
+
+                    let remove_button = Button::icon_only(IconType::Close)
+                        .size(ButtonSize::Small)
+                        .tooltip("Remove pattern");
+
+                    if remove_button.show(ui, &mut self.icon_manager).clicked() {
+                        pattern_to_remove = Some(idx);
+                    }
+                });
+            }
+            if let Some(idx) = pattern_to_remove {
+                secrets.custom_patterns.remove(idx);
+            }
+
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.new_secret_pattern_input)
+                        .hint_text("Extra pattern (regex)")
+                        .desired_width(250.0),
+                );
+
+                let add_button = Button::new("+")
+                    .variant(ButtonVariant::Primary)
+                    .size(ButtonSize::Small)
+                    .tooltip("Add secret pattern");
+
+                let pattern = self.new_secret_pattern_input.trim();
+                if (add_button.show(ui, &mut self.icon_manager).clicked()
+                    || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))))
+                    && !pattern.is_empty()
+                {
+                    match secrets::compile_pattern(pattern) {
+                        Ok(_) => {
+                            secrets.custom_patterns.push(pattern.to_string());
+                            self.new_secret_pattern_input.clear();
+                        }
+                        Err(e) => self.toast_manager.error(e),
+                    }
+                }
+            });
+        });
+    }
+
+    /// Shows the redacted secrets for review before the output is copied
+    pub fn show_secret_review(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut copy = false;
+        let mut cancel = false;
+
+        egui::Window::new("Review Redacted Secrets")
+            .id(egui::Id::new("secret_review"))
+            .open(&mut open)
+            .resizable(false)
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} secret(s) were replaced with [REDACTED:kind] markers:",
+                    self.state.output.secrets.len()
+                ));
+                ui.add_space(UiTheme::SPACING_SM);
+
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("secret_findings")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for finding in &self.state.output.secrets {
+                                    ui.monospace(format!("{}:{}", finding.path, finding.line));
+                                    ui.label(finding.kind.label());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.add_space(UiTheme::SPACING_SM);
+                ui.horizontal(|ui| {
+                    let copy_button = Button::new("Copy")
+                        .variant(ButtonVariant::Primary)
+                        .size(ButtonSize::Small)
+                        .tooltip("Copy the redacted output");
+                    copy = copy_button.show(ui, &mut self.icon_manager).clicked();
+
+                    let cancel_button = Button::new("Cancel")
+                        .variant(ButtonVariant::Ghost)
+                        .size(ButtonSize::Small);
+                    cancel = cancel_button.show(ui, &mut self.icon_manager).clicked();
+                });
+            });
+
+        if copy {
+            self.state.output.secrets_reviewed = true;
+            self.state.output.show_secret_review = false;
+            self.copy_to_clipboard();
+        } else if !open || cancel || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.state.output.show_secret_review = false;
+        }
+    }
+
     /// Shows an empty state when no directory is selected
     fn show_empty_state(&mut self, ui: &mut egui::Ui) {
         ui.centered_and_justified(|ui| {
@@ -18,6 +18,8 @@ pub struct Footer<'a> {
     icon_manager: &'a mut IconManager,
     current_progress: &'a Option<(ProgressStage, ProgressCount)>,
     on_generate: Option<Box<dyn FnOnce() + 'a>>,
+    /// Hover text of the format picker when the project config sets the format
+    format_set_by: Option<String>,
 }
 
 impl<'a> Footer<'a> {
@@ -35,9 +37,17 @@ impl<'a> Footer<'a> {
             icon_manager,
             current_progress,
             on_generate: None,
+            format_set_by: None,
         }
     }
 
+    /// Disables the format picker, explaining why on hover
+    #[must_use]
+    pub fn format_set_by(mut self, hover_text: Option<String>) -> Self {
+        self.format_set_by = hover_text;
+        self
+    }
+
     /// Sets the callback to run when generate is clicked
     pub fn on_generate(mut self, callback: impl FnOnce() + 'a) -> Self {
         self.on_generate = Some(Box::new(callback));
@@ -86,8 +96,17 @@ impl<'a> Footer<'a> {
 
             ui.add_space(tokens.spacing.sm);
 
-            // Token count with enhanced visual indicator
-            let (token_color, token_bg, token_label) = if token_estimate < 10_000 {
+            // Token count with enhanced visual indicator, measured against
+            // the token budget when one is set
+            let budget = self.state.config.output.token_budget;
+            let over_budget = budget.enabled && token_estimate > budget.max_tokens;
+            let (token_color, token_bg, token_label) = if over_budget {
+                (
+                    tokens.colors.error,
+                    tokens.colors.error_container,
+                    "Over budget",
+                )
+            } else if token_estimate < 10_000 {
                 (
                     tokens.colors.success,
                     tokens.colors.success_container,
@@ -102,6 +121,18 @@ impl<'a> Footer<'a> {
             } else {
                 (tokens.colors.error, tokens.colors.error_container, "High")
             };
+            let token_text = if budget.enabled {
+                format!(
+                    "~{} / {} tokens ({token_label})",
+                    format_token_count(token_estimate),
+                    format_token_count(budget.max_tokens)
+                )
+            } else {
+                format!(
+                    "~{} tokens ({token_label})",
+                    format_token_count(token_estimate)
+                )
+            };
 
             egui::Frame::new()
                 .fill(token_bg.gamma_multiply(0.3))
@@ -111,14 +142,15 @@ impl<'a> Footer<'a> {
                 ))
                 .corner_radius(tokens.radius.sm)
                 .show(ui, |ui| {
-                    ui.label(
-                        egui::RichText::new(format!(
-                            "~{} tokens ({})",
-                            format_token_count(token_estimate),
-                            token_label
-                        ))
-                        .color(token_color),
-                    );
+                    let response = ui.label(egui::RichText::new(token_text).color(token_color));
+                    if over_budget {
+                        response.on_hover_text(
+                            "Files will be dropped or truncated to fit the token budget",
+                        );
+                    }
+                    if self.state.output.counting_tokens {
+                        ui.spinner();
+                    }
                 });
 
             ui.separator();
@@ -127,12 +159,29 @@ impl<'a> Footer<'a> {
             ui.label(egui::RichText::new("Format:").color(tokens.colors.on_surface_variant));
 
             // Create a custom segmented control with better alignment
-            let format_control = SegmentedControl::new(self.state.output.format)
+            let mut format_control = SegmentedControl::new(self.state.output.format.clone())
                 .option(OutputFormat::Xml, "XML", None)
                 .option(OutputFormat::Markdown, "MD", None)
+                .option(OutputFormat::Json, "JSON", None)
                 .size(ButtonSize::Small);
 
-            if let Some(new_format) = format_control.show(ui, self.icon_manager) {
+            // User-defined templates appear as extra formats
+            for template in &self.state.output.templates {
+                format_control = format_control.option(
+                    OutputFormat::Template(Box::new(template.clone())),
+                    template.name.clone(),
+                    None,
+                );
+            }
+
+            let picker = ui.add_enabled_ui(self.format_set_by.is_none(), |ui| {
+                format_control.show(ui, self.icon_manager)
+            });
+            if let Some(hover_text) = &self.format_set_by {
+                picker.response.on_disabled_hover_text(hover_text);
+            }
+            if let Some(new_format) = picker.inner {
+                self.state.config.ui.output_format = new_format.clone();
                 self.state.output.format = new_format;
             }
         });
@@ -163,6 +212,7 @@ impl<'a> Footer<'a> {
                 let stage_text = match stage {
                     ProgressStage::ScanningFiles => "Scanning",
                     ProgressStage::ReadingFiles => "Reading",
+                    ProgressStage::ReadingGitHistory => "Reading git",
                     ProgressStage::BuildingOutput => "Building",
                 };
                 ui.label(format!("{stage_text}: {:.0}%", progress.percentage()));
@@ -203,15 +253,7 @@ impl<'a> Footer<'a> {
     }
 
     fn estimate_tokens_for_selection(&self) -> usize {
-        self.state.output.estimated_tokens.unwrap_or_else(|| {
-            let selected_files = self.tree.get_selected_files();
-            // Rough estimate: 1 token per 4 characters
-            selected_files
-                .iter()
-                .filter_map(|path| std::fs::metadata(path).ok())
-                .map(|metadata| (metadata.len() / 4) as usize)
-                .sum()
-        })
+        self.state.output.estimated_tokens.unwrap_or_default()
     }
 }
 
@@ -1,5 +1,5 @@
 use crate::{
-    core::types::AppState,
+    core::types::{AppState, CanonicalPath},
     ui::{
         components::{Button, ButtonSize, ButtonVariant},
         icons::{IconManager, IconType},
@@ -8,12 +8,14 @@ use crate::{
     },
 };
 use eframe::egui;
+use std::path::PathBuf;
 
 /// App header component
 pub struct AppHeader<'a> {
     state: &'a mut AppState,
     icon_manager: &'a mut IconManager,
     on_select_directory: Option<Box<dyn FnOnce() + 'a>>,
+    on_open_recent: Option<Box<dyn FnOnce(PathBuf) + 'a>>,
 }
 
 impl<'a> AppHeader<'a> {
@@ -22,6 +24,7 @@ impl<'a> AppHeader<'a> {
             state,
             icon_manager,
             on_select_directory: None,
+            on_open_recent: None,
         }
     }
 
@@ -31,6 +34,13 @@ impl<'a> AppHeader<'a> {
         self
     }
 
+    /// Sets the callback to run when a recent directory is picked
+    #[must_use]
+    pub fn on_open_recent(mut self, callback: impl FnOnce(PathBuf) + 'a) -> Self {
+        self.on_open_recent = Some(Box::new(callback));
+        self
+    }
+
     /// Shows the app header
     pub fn show(mut self, ctx: &egui::Context) {
         let dark_mode = ctx.style().visuals.dark_mode;
@@ -71,6 +81,8 @@ impl<'a> AppHeader<'a> {
                             }
                         }
 
+                        self.show_recent_menu(ui);
+
                         // Show directory path to the right of the button
                         if let Some(root) = &self.state.root {
                             ui.add_space(tokens.spacing.md);
@@ -92,4 +104,30 @@ impl<'a> AppHeader<'a> {
                 });
             });
     }
+
+    /// Shows the menu of recently opened directories, other than the current one
+    fn show_recent_menu(&mut self, ui: &mut egui::Ui) {
+        let current = self.state.root.as_ref().map(CanonicalPath::as_path);
+        let recent: Vec<PathBuf> = self
+            .state
+            .existing_recent_directories
+            .iter()
+            .filter(|path| Some(path.as_path()) != current)
+            .cloned()
+            .collect();
+        if recent.is_empty() {
+            return;
+        }
+
+        ui.menu_button("Recent", |ui| {
+            for path in recent {
+                if ui.button(path.display().to_string()).clicked() {
+                    if let Some(callback) = self.on_open_recent.take() {
+                        callback(path);
+                    }
+                    ui.close_menu();
+                }
+            }
+        });
+    }
 }
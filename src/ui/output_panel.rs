@@ -77,11 +77,49 @@ impl<'a> OutputPanel<'a> {
                     if copy_button.show(ui, &mut self.app.icon_manager).clicked() {
                         self.app.copy_to_clipboard();
                     }
+
+                    self.show_part_navigation(ui);
                 }
             });
         });
     }
 
+    /// Shows next/previous controls when the output was split into parts
+    ///
+    /// Laid out right to left, so the next button is added first.
+    fn show_part_navigation(&mut self, ui: &mut egui::Ui) {
+        let part_count = self.app.state.output.parts.len();
+        if part_count <= 1 {
+            return;
+        }
+        let current = self.app.state.output.current_part;
+
+        ui.separator();
+
+        let next_button = Button::ghost("▶")
+            .size(ButtonSize::Small)
+            .min_width(28.0)
+            .disabled(current + 1 >= part_count)
+            .tooltip("Next part");
+        if next_button.show(ui, &mut self.app.icon_manager).clicked() {
+            self.app.state.output.show_part(current + 1);
+        }
+
+        ui.label(format!("Part {} of {part_count}", current + 1));
+
+        let previous_button = Button::ghost("◀")
+            .size(ButtonSize::Small)
+            .min_width(28.0)
+            .disabled(current == 0)
+            .tooltip("Previous part");
+        if previous_button
+            .show(ui, &mut self.app.icon_manager)
+            .clicked()
+        {
+            self.app.state.output.show_part(current.saturating_sub(1));
+        }
+    }
+
     /// Shows the output content area
     fn show_content(&self, ui: &mut egui::Ui) {
         let tokens = UiTheme::design_tokens(ui.visuals().dark_mode);
@@ -3,8 +3,8 @@
 use eframe::egui;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
-use glob::Pattern;
 use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use crate::core::types::{CanonicalPath, FileSize};
 use crate::ui::{
@@ -12,12 +12,16 @@ use crate::ui::{
     icons::{IconManager, IconType},
     Theme,
 };
+use crate::utils::content;
+use crate::utils::git::{GitChanges, GitStatus};
+use crate::utils::ignore_rules::IgnoreRules;
 
 // Using SelectionState from core::types
 pub use crate::core::types::SelectionState;
 
 /// A node in the directory tree
 #[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)] // independent per-node flags
 pub struct TreeNode {
     /// Canonical path to the file or directory
     pub canonical_path: CanonicalPath,
@@ -35,6 +39,8 @@ pub struct TreeNode {
     pub children: Vec<TreeNode>,
     /// File size if this is a file
     pub file_size: Option<FileSize>,
+    /// Whether the file looks binary, sniffed when first needed, see [`Self::is_binary`]
+    pub binary: Option<bool>,
 }
 
 impl TreeNode {
@@ -71,16 +77,29 @@ impl TreeNode {
             children_loaded: false,
             children: Vec::new(),
             file_size,
+            binary: None,
+        })
+    }
+
+    /// Returns whether the file looks binary and will be skipped during generation
+    ///
+    /// The file is sniffed on the first call and the result is cached.
+    pub fn is_binary(&mut self) -> bool {
+        if self.is_dir {
+            return false;
+        }
+        *self.binary.get_or_insert_with(|| {
+            content::sniff_file(self.canonical_path.as_path()).unwrap_or(false)
         })
     }
 
     /// Loads children for this node if it's a directory
     pub fn load_children(&mut self) {
-        self.load_children_with_patterns(&[]);
+        self.load_children_with_rules(&IgnoreRules::none());
     }
 
-    /// Loads children for this node with ignore patterns
-    pub fn load_children_with_patterns(&mut self, ignore_patterns: &[glob::Pattern]) {
+    /// Loads children for this node, leaving out entries hidden by `rules`
+    pub fn load_children_with_rules(&mut self, rules: &IgnoreRules) {
         if !self.is_dir || self.children_loaded {
             return;
         }
@@ -92,13 +111,8 @@ impl TreeNode {
                 .filter_map(Result::ok)
                 .filter_map(|entry| {
                     let path = entry.path();
-                    let name = path.file_name()?.to_str()?;
-
-                    // Check if this entry should be ignored
-                    for pattern in ignore_patterns {
-                        if pattern.matches(name) {
-                            return None;
-                        }
+                    if rules.is_ignored(&path, path.is_dir()) {
+                        return None;
                     }
 
                     CanonicalPath::new(path)
@@ -120,15 +134,15 @@ impl TreeNode {
 
     /// Loads all children recursively up to a maximum depth
     pub fn load_children_recursive(&mut self, current_depth: usize, max_depth: usize) {
-        self.load_children_recursive_with_patterns(current_depth, max_depth, &[]);
+        self.load_children_recursive_with_rules(current_depth, max_depth, &IgnoreRules::none());
     }
 
-    /// Loads all children recursively up to a maximum depth with ignore patterns
-    pub fn load_children_recursive_with_patterns(
+    /// Loads all children recursively up to a maximum depth, leaving out entries hidden by `rules`
+    pub fn load_children_recursive_with_rules(
         &mut self,
         current_depth: usize,
         max_depth: usize,
-        ignore_patterns: &[glob::Pattern],
+        rules: &IgnoreRules,
     ) {
         if !self.is_dir || current_depth >= max_depth {
             return;
@@ -136,7 +150,7 @@ impl TreeNode {
 
         // Load immediate children if not already loaded
         if !self.children_loaded {
-            self.load_children_with_patterns(ignore_patterns);
+            self.load_children_with_rules(rules);
         }
 
         // Expand this directory to show its contents
@@ -145,36 +159,32 @@ impl TreeNode {
         // Recursively load children of subdirectories
         for child in &mut self.children {
             if child.is_dir {
-                child.load_children_recursive_with_patterns(
-                    current_depth + 1,
-                    max_depth,
-                    ignore_patterns,
-                );
+                child.load_children_recursive_with_rules(current_depth + 1, max_depth, rules);
             }
         }
     }
 
     /// Updates selection state recursively
     pub fn set_selection(&mut self, state: SelectionState) {
-        self.set_selection_with_patterns(state, &[]);
+        self.set_selection_with_rules(state, &IgnoreRules::none());
     }
 
-    /// Updates selection state recursively with ignore patterns
-    pub fn set_selection_with_patterns(&mut self, state: SelectionState, patterns: &[Pattern]) {
+    /// Updates selection state recursively, loading children hidden by `rules` only
+    pub fn set_selection_with_rules(&mut self, state: SelectionState, rules: &IgnoreRules) {
         self.selection = state;
 
-        // If setting to checked/unchecked, propagate to all children
+        // If setting to checked/outline/unchecked, propagate to all children
         if state != SelectionState::Indeterminate {
-            // If this is a directory being checked, load all children recursively
-            if state == SelectionState::Checked && self.is_dir {
+            // If this is a directory being selected, load all children recursively
+            if matches!(state, SelectionState::Checked | SelectionState::Outline) && self.is_dir {
                 // Load all descendants up to 20 levels deep (reasonable limit)
-                self.load_children_recursive_with_patterns(0, 20, patterns);
+                self.load_children_recursive_with_rules(0, 20, rules);
                 // Also expand this node to show what was selected
                 self.expanded = true;
             }
 
             for child in &mut self.children {
-                child.set_selection_with_patterns(state, patterns);
+                child.set_selection_with_rules(state, rules);
             }
         }
     }
@@ -189,6 +199,10 @@ impl TreeNode {
             .children
             .iter()
             .all(|c| c.selection == SelectionState::Checked);
+        let all_outline = self
+            .children
+            .iter()
+            .all(|c| c.selection == SelectionState::Outline);
         let all_unchecked = self
             .children
             .iter()
@@ -196,6 +210,8 @@ impl TreeNode {
 
         self.selection = if all_checked {
             SelectionState::Checked
+        } else if all_outline {
+            SelectionState::Outline
         } else if all_unchecked {
             SelectionState::Unchecked
         } else {
@@ -248,6 +264,10 @@ struct FlattenedNode {
     is_expanded: bool,
     /// Selection state
     selection: SelectionState,
+    /// Path of the node, for lookups such as pinning
+    canonical_path: CanonicalPath,
+    /// Whether the file looks binary, `None` until the row is first shown
+    binary: Option<bool>,
 }
 
 /// Directory tree widget
@@ -258,13 +278,21 @@ pub struct DirectoryTree {
     /// Map of path to node for quick lookups
     node_map: HashMap<CanonicalPath, usize>,
     /// Ignore patterns to filter files/directories
-    ignore_patterns: Vec<Pattern>,
+    ignore_patterns: Vec<String>,
+    /// Whether `.gitignore` and the other ignore files are honoured
+    respect_ignore_files: bool,
+    /// Rules built from the patterns and ignore files for the current root
+    rules: Arc<IgnoreRules>,
     /// Flattened view of visible nodes (cached)
     flattened_nodes: Vec<FlattenedNode>,
     /// Whether the flattened view needs rebuilding
     needs_flattening: bool,
     /// Animation states for expand/collapse
     expansion_animations: HashMap<Vec<usize>, f32>,
+    /// Files kept when packing the selection into a token budget
+    pinned: HashSet<CanonicalPath>,
+    /// Git status of changed files, if the root is inside a repository
+    git_changes: Option<GitChanges>,
 }
 
 impl DirectoryTree {
@@ -275,21 +303,34 @@ impl DirectoryTree {
             roots: Vec::new(),
             node_map: HashMap::new(),
             ignore_patterns: Vec::new(),
+            respect_ignore_files: true,
+            rules: Arc::new(IgnoreRules::none()),
             flattened_nodes: Vec::new(),
             needs_flattening: true,
             expansion_animations: HashMap::new(),
+            pinned: HashSet::new(),
+            git_changes: None,
         }
     }
 
     /// Sets the root directory for the tree
     pub fn set_root(&mut self, path: CanonicalPath) {
+        // Pins survive a refresh of the same directory
+        if self.roots.first().map(|root| &root.canonical_path) != Some(&path) {
+            self.pinned.clear();
+        }
         self.roots.clear();
         self.node_map.clear();
         self.needs_flattening = true;
 
+        self.rules = Arc::new(IgnoreRules::new(
+            path.as_path(),
+            &self.ignore_patterns,
+            self.respect_ignore_files,
+        ));
         if let Ok(mut root) = TreeNode::new(path) {
             root.expanded = true;
-            root.load_children_with_patterns(&self.ignore_patterns);
+            root.load_children_with_rules(&self.rules);
             self.roots.push(root);
         }
     }
@@ -300,29 +341,52 @@ impl DirectoryTree {
             .split(',')
             .map(str::trim)
             .filter(|s| !s.is_empty())
-            .filter_map(|pattern| Pattern::new(pattern).ok())
+            .map(str::to_string)
             .collect();
+        self.rebuild_rules();
+    }
+
+    /// User patterns that were skipped because they failed to compile
+    #[must_use]
+    pub fn invalid_patterns(&self) -> &[String] {
+        self.rules.invalid_patterns()
+    }
 
-        // Reload all expanded directories with new patterns
-        if !self.roots.is_empty() {
-            Self::reload_with_patterns(&mut self.roots[0], &self.ignore_patterns);
-            self.needs_flattening = true;
+    /// Sets whether `.gitignore` and the other ignore files are honoured
+    pub fn set_respect_ignore_files(&mut self, respect: bool) {
+        if self.respect_ignore_files != respect {
+            self.respect_ignore_files = respect;
+            self.rebuild_rules();
         }
     }
 
-    /// Recursively reloads expanded directories with new patterns
-    fn reload_with_patterns(node: &mut TreeNode, patterns: &[Pattern]) {
+    /// Rebuilds the ignore rules and reloads all expanded directories with them
+    fn rebuild_rules(&mut self) {
+        let Some(root) = self.roots.first_mut() else {
+            return;
+        };
+        self.rules = Arc::new(IgnoreRules::new(
+            root.canonical_path.as_path(),
+            &self.ignore_patterns,
+            self.respect_ignore_files,
+        ));
+        Self::reload_with_rules(root, &self.rules);
+        self.needs_flattening = true;
+    }
+
+    /// Recursively reloads expanded directories with new rules
+    fn reload_with_rules(node: &mut TreeNode, rules: &IgnoreRules) {
         if node.is_dir && node.children_loaded {
-            // Clear children and reload with patterns
+            // Clear children and reload with the rules
             node.children.clear();
             node.children_loaded = false;
-            node.load_children_with_patterns(patterns);
+            node.load_children_with_rules(rules);
 
             // If node was expanded, reload children recursively
             if node.expanded {
                 for child in &mut node.children {
                     if child.is_dir {
-                        Self::reload_with_patterns(child, patterns);
+                        Self::reload_with_rules(child, rules);
                     }
                 }
             }
@@ -406,6 +470,8 @@ impl DirectoryTree {
             is_dir: node.is_dir,
             is_expanded: node.expanded,
             selection: node.selection,
+            canonical_path: node.canonical_path.clone(),
+            binary: node.binary,
         });
 
         // If expanded, add children
@@ -425,6 +491,23 @@ impl DirectoryTree {
         }
     }
 
+    /// Returns whether the file in a flattened row looks binary, sniffing it when first shown
+    fn sniff_row(&mut self, row: usize) -> bool {
+        let flat_node = &self.flattened_nodes[row];
+        if flat_node.is_dir {
+            return false;
+        }
+        if let Some(binary) = flat_node.binary {
+            return binary;
+        }
+        let node_path = flat_node.node_path.clone();
+        let binary = self
+            .get_node_by_path_mut(&node_path)
+            .is_some_and(TreeNode::is_binary);
+        self.flattened_nodes[row].binary = Some(binary);
+        binary
+    }
+
     /// Gets a mutable reference to a node by its path
     fn get_node_by_path_mut(&mut self, path: &[usize]) -> Option<&mut TreeNode> {
         if path.is_empty() || self.roots.is_empty() {
@@ -459,6 +542,121 @@ impl DirectoryTree {
         self.needs_flattening = true;
     }
 
+    /// Selects exactly the given files, expanding the directories above them
+    ///
+    /// Returns how many of the files were found; files outside the root or
+    /// hidden by ignore patterns are skipped.
+    pub fn select_paths(&mut self, paths: &[std::path::PathBuf]) -> usize {
+        self.deselect_all();
+        self.mark_paths(paths, SelectionState::Checked, true)
+    }
+
+    /// Checks or unchecks the given files, leaving the rest of the selection as it is
+    ///
+    /// Directories above the files are loaded but not expanded. Returns how
+    /// many of the files were found; files outside the root or hidden by
+    /// ignore patterns are skipped.
+    pub fn set_paths_selected(&mut self, paths: &[std::path::PathBuf], selected: bool) -> usize {
+        let state = SelectionState::from_bool(selected);
+        self.mark_paths(paths, state, false)
+    }
+
+    /// Sets the selection of the given files, loading the directories above them
+    fn mark_paths(
+        &mut self,
+        paths: &[std::path::PathBuf],
+        state: SelectionState,
+        expand: bool,
+    ) -> usize {
+        let Some(root) = self.roots.first_mut() else {
+            return 0;
+        };
+
+        let mut found = 0;
+        for path in paths {
+            let Some(node) = Self::load_node(root, path, &self.rules, expand) else {
+                continue;
+            };
+            if !node.is_dir {
+                // Files already selected as an outline stay so
+                if !(state.is_selected() && node.selection.is_selected()) {
+                    node.selection = state;
+                }
+                found += 1;
+            }
+        }
+
+        Self::update_parent_states_recursive(root);
+        self.needs_flattening = true;
+        found
+    }
+
+    /// Returns the files in the directories containing the given files, sorted
+    ///
+    /// The directories are loaded with the ignore rules of the tree, so
+    /// ignored siblings are left out.
+    pub fn sibling_files(&mut self, paths: &[std::path::PathBuf]) -> Vec<std::path::PathBuf> {
+        let Some(root) = self.roots.first_mut() else {
+            return Vec::new();
+        };
+
+        let mut files = std::collections::BTreeSet::new();
+        let parents: std::collections::BTreeSet<_> =
+            paths.iter().filter_map(|path| path.parent()).collect();
+        for parent in parents {
+            let Some(dir) = Self::load_node(root, parent, &self.rules, false) else {
+                continue;
+            };
+            if !dir.children_loaded {
+                dir.load_children_with_rules(&self.rules);
+            }
+            files.extend(
+                dir.children
+                    .iter()
+                    .filter(|child| !child.is_dir)
+                    .map(|child| child.canonical_path.as_path().to_path_buf()),
+            );
+        }
+        files.into_iter().collect()
+    }
+
+    /// Finds the node at `path`, loading the directories above it
+    ///
+    /// Returns `None` if the path is outside the root or hidden by ignore rules.
+    fn load_node<'a>(
+        root: &'a mut TreeNode,
+        path: &std::path::Path,
+        rules: &IgnoreRules,
+        expand: bool,
+    ) -> Option<&'a mut TreeNode> {
+        let relative = path.strip_prefix(root.canonical_path.as_path()).ok()?;
+        let mut node = root;
+        for component in relative.components() {
+            if !node.children_loaded {
+                node.load_children_with_rules(rules);
+            }
+            node.expanded |= expand;
+            let name = component.as_os_str();
+            let index = node
+                .children
+                .iter()
+                .position(|child| name == child.name.as_str())?;
+            node = &mut node.children[index];
+        }
+        Some(node)
+    }
+
+    /// Sets the git status shown next to changed files
+    pub fn set_git_changes(&mut self, changes: Option<GitChanges>) {
+        self.git_changes = changes;
+    }
+
+    /// Returns whether the root is inside a git repository
+    #[must_use]
+    pub const fn has_git_changes(&self) -> bool {
+        self.git_changes.is_some()
+    }
+
     /// Recursively sets selection state
     fn set_selection_recursive(node: &mut TreeNode, state: SelectionState) {
         node.selection = state;
@@ -513,7 +711,7 @@ impl DirectoryTree {
         egui::ScrollArea::vertical()
             .auto_shrink([false, false])
             .show_rows(ui, row_height, total_rows, |ui, row_range| {
-                let patterns = self.ignore_patterns.clone();
+                let rules = Arc::clone(&self.rules);
                 let mut any_selection_changed = false;
                 let mut any_expansion_changed = false;
 
@@ -522,6 +720,7 @@ impl DirectoryTree {
                         break;
                     }
 
+                    let is_binary = self.sniff_row(row);
                     let flat_node = self.flattened_nodes[row].clone();
 
                     // Debug: print node info to identify empty rows
@@ -584,7 +783,7 @@ impl DirectoryTree {
                                     {
                                         node.expanded = !node.expanded;
                                         if node.expanded && !node.children_loaded {
-                                            node.load_children_with_patterns(&patterns);
+                                            node.load_children_with_rules(&rules);
                                         }
                                         any_expansion_changed = true;
 
@@ -627,6 +826,7 @@ impl DirectoryTree {
                                 checkbox_anim_id,
                                 match flat_node.selection {
                                     SelectionState::Checked => 1.0,
+                                    SelectionState::Outline => 0.75,
                                     SelectionState::Indeterminate => 0.5,
                                     SelectionState::Unchecked => 0.0,
                                 },
@@ -675,6 +875,16 @@ impl DirectoryTree {
                                         egui::Stroke::new(1.5, egui::Color32::WHITE),
                                     ));
                                 }
+                                SelectionState::Outline => {
+                                    // Draw outline lines, indented like declarations
+                                    for (x, y) in [(3.0, 4.0), (5.5, 7.0), (3.0, 10.0)] {
+                                        let start = checkbox_rect.min + egui::vec2(x, y);
+                                        ui.painter().line_segment(
+                                            [start, egui::pos2(checkbox_rect.max.x - 3.0, start.y)],
+                                            egui::Stroke::new(1.5, egui::Color32::WHITE),
+                                        );
+                                    }
+                                }
                                 SelectionState::Indeterminate => {
                                     // Draw dash
                                     let dash_rect = checkbox_rect.shrink(4.0);
@@ -692,17 +902,28 @@ impl DirectoryTree {
                                 }
                             }
 
+                            let checkbox_response =
+                                if flat_node.selection == SelectionState::Outline {
+                                    checkbox_response.on_hover_text(
+                                        "Included as an outline: function bodies are collapsed",
+                                    )
+                                } else {
+                                    checkbox_response
+                                };
+
                             // Handle click
                             if checkbox_response.clicked() {
                                 let new_state = match flat_node.selection {
                                     SelectionState::Unchecked => SelectionState::Checked,
-                                    SelectionState::Checked => SelectionState::Unchecked,
+                                    SelectionState::Checked | SelectionState::Outline => {
+                                        SelectionState::Unchecked
+                                    }
                                     SelectionState::Indeterminate => SelectionState::Checked,
                                 };
 
                                 if let Some(node) = self.get_node_by_path_mut(&flat_node.node_path)
                                 {
-                                    node.set_selection_with_patterns(new_state, &patterns);
+                                    node.set_selection_with_rules(new_state, &rules);
                                     any_selection_changed = true;
                                 }
                             }
@@ -718,6 +939,14 @@ impl DirectoryTree {
                                         .color(tokens.colors.on_surface),
                                     Some(tokens.colors.primary),
                                 )
+                            } else if is_binary {
+                                (
+                                    egui::RichText::new(&flat_node.name)
+                                        .size(12.0) // Smaller text
+                                        .italics()
+                                        .color(tokens.colors.outline),
+                                    Some(tokens.colors.outline),
+                                )
                             } else {
                                 (
                                     egui::RichText::new(&flat_node.name)
@@ -745,7 +974,77 @@ impl DirectoryTree {
                                 icon_tint,
                             );
                             ui.add_space(2.0); // Minimal spacing between icon and text
-                            ui.label(text_style);
+
+                            let mut label =
+                                ui.add(egui::Label::new(text_style).sense(egui::Sense::click()));
+                            if is_binary {
+                                label = label.on_hover_text(
+                                    "Binary file: skipped during generation unless binary placeholders are enabled",
+                                );
+                            }
+                            let pinned =
+                                !flat_node.is_dir && self.pinned.contains(&flat_node.canonical_path);
+                            if pinned {
+                                ui.label(egui::RichText::new("📌").size(10.0))
+                                    .on_hover_text("Pinned: always kept within the token budget");
+                            }
+                            if let Some(changes) = &self.git_changes {
+                                let path = flat_node.canonical_path.as_path();
+                                if let Some(status) = changes.status(path) {
+                                    let color = match status {
+                                        GitStatus::Modified => tokens.colors.warning,
+                                        GitStatus::Added => tokens.colors.success,
+                                        GitStatus::Untracked => tokens.colors.on_surface_variant,
+                                    };
+                                    ui.label(
+                                        egui::RichText::new(status.badge())
+                                            .size(10.0)
+                                            .strong()
+                                            .color(color),
+                                    )
+                                    .on_hover_text(status.label());
+                                } else if flat_node.is_dir && changes.contains_changes(path) {
+                                    ui.label(
+                                        egui::RichText::new("•")
+                                            .size(10.0)
+                                            .color(tokens.colors.warning),
+                                    )
+                                    .on_hover_text("Contains changed files");
+                                }
+                            }
+                            label.context_menu(|ui| {
+                                let outlined = flat_node.selection == SelectionState::Outline;
+                                let text = if outlined {
+                                    "Include full contents"
+                                } else {
+                                    "Include as outline"
+                                };
+                                if ui.button(text).clicked() {
+                                    let state = if outlined {
+                                        SelectionState::Checked
+                                    } else {
+                                        SelectionState::Outline
+                                    };
+                                    if let Some(node) =
+                                        self.get_node_by_path_mut(&flat_node.node_path)
+                                    {
+                                        node.set_selection_with_rules(state, &rules);
+                                        any_selection_changed = true;
+                                    }
+                                    ui.close_menu();
+                                }
+                                if !flat_node.is_dir {
+                                    let text = if pinned {
+                                        "Unpin"
+                                    } else {
+                                        "Pin to token budget"
+                                    };
+                                    if ui.button(text).clicked() {
+                                        self.toggle_pin(&flat_node.canonical_path);
+                                        ui.close_menu();
+                                    }
+                                }
+                            });
                         });
                     });
                 }
@@ -831,7 +1130,7 @@ impl DirectoryTree {
     /// Helper function to collect selected files from a node recursively
     fn collect_selected_from_node(node: &TreeNode, selected: &mut Vec<CanonicalPath>) {
         match node.selection {
-            SelectionState::Checked => {
+            SelectionState::Checked | SelectionState::Outline => {
                 if node.is_dir {
                     // For directories, collect all files recursively
                     for child in &node.children {
@@ -878,7 +1177,42 @@ impl DirectoryTree {
         false
     }
 
-    /// Gets all selected file paths as a set
+    /// Pins or unpins a file for token budget packing
+    pub fn toggle_pin(&mut self, path: &CanonicalPath) {
+        if !self.pinned.remove(path) {
+            self.pinned.insert(path.clone());
+        }
+    }
+
+    /// Gets the files pinned for token budget packing
+    #[must_use]
+    pub fn pinned_files(&self) -> Vec<CanonicalPath> {
+        self.pinned.iter().cloned().collect()
+    }
+
+    /// Gets the files selected as an outline
+    #[must_use]
+    pub fn outline_files(&self) -> Vec<CanonicalPath> {
+        let mut outline = Vec::new();
+        for root in &self.roots {
+            Self::collect_outline_from_node(root, &mut outline);
+        }
+        outline
+    }
+
+    /// Collects files selected as an outline recursively
+    fn collect_outline_from_node(node: &TreeNode, outline: &mut Vec<CanonicalPath>) {
+        if node.selection == SelectionState::Outline && !node.is_dir {
+            outline.push(node.canonical_path.clone());
+        }
+        if node.selection != SelectionState::Unchecked {
+            for child in &node.children {
+                Self::collect_outline_from_node(child, outline);
+            }
+        }
+    }
+
+    /// Gets all selected file paths as a set, including outlined files
     pub fn get_selected_files(&self) -> HashSet<String> {
         let mut selected = HashSet::new();
         for root in &self.roots {
@@ -887,6 +1221,14 @@ impl DirectoryTree {
         selected
     }
 
+    /// Gets the paths of files selected as an outline as a set
+    pub fn get_outline_files(&self) -> HashSet<String> {
+        self.outline_files()
+            .iter()
+            .map(|path| path.as_path().to_string_lossy().to_string())
+            .collect()
+    }
+
     /// Gets all expanded directory paths as a set
     pub fn get_expanded_dirs(&self) -> HashSet<String> {
         let mut expanded = HashSet::new();
@@ -897,13 +1239,23 @@ impl DirectoryTree {
     }
 
     /// Restores selection and expansion state
+    ///
+    /// Files in `outline_files` are selected as an outline and files in
+    /// neither set are deselected.
     pub fn restore_selection(
         &mut self,
         selected_files: &HashSet<String>,
+        outline_files: &HashSet<String>,
         expanded_dirs: &HashSet<String>,
     ) {
         for root in &mut self.roots {
-            Self::restore_node_state_recursive(root, selected_files, expanded_dirs);
+            Self::restore_node_state_recursive(
+                root,
+                selected_files,
+                outline_files,
+                expanded_dirs,
+                &self.rules,
+            );
         }
 
         // Update parent states after restoring
@@ -916,7 +1268,7 @@ impl DirectoryTree {
 
     /// Collects selected file paths recursively
     fn collect_selected_paths_recursive(node: &TreeNode, selected: &mut HashSet<String>) {
-        if node.selection == SelectionState::Checked && !node.is_dir {
+        if node.selection.is_selected() && !node.is_dir {
             selected.insert(node.canonical_path.as_path().to_string_lossy().to_string());
         }
 
@@ -940,7 +1292,9 @@ impl DirectoryTree {
     fn restore_node_state_recursive(
         node: &mut TreeNode,
         selected_files: &HashSet<String>,
+        outline_files: &HashSet<String>,
         expanded_dirs: &HashSet<String>,
+        rules: &IgnoreRules,
     ) {
         let path_str = node.canonical_path.as_path().to_string_lossy().to_string();
 
@@ -949,18 +1303,30 @@ impl DirectoryTree {
             node.expanded = true;
             // Load children if not already loaded
             if !node.children_loaded {
-                node.load_children();
+                node.load_children_with_rules(rules);
             }
         }
 
         // Restore selection state
-        if !node.is_dir && selected_files.contains(&path_str) {
-            node.selection = SelectionState::Checked;
+        if !node.is_dir {
+            node.selection = if outline_files.contains(&path_str) {
+                SelectionState::Outline
+            } else if selected_files.contains(&path_str) {
+                SelectionState::Checked
+            } else {
+                SelectionState::Unchecked
+            };
         }
 
         // Recursively restore children
         for child in &mut node.children {
-            Self::restore_node_state_recursive(child, selected_files, expanded_dirs);
+            Self::restore_node_state_recursive(
+                child,
+                selected_files,
+                outline_files,
+                expanded_dirs,
+                rules,
+            );
         }
     }
 
@@ -0,0 +1,138 @@
+//! Sniffing file contents before they are decoded
+//!
+//! Only the first [`SNIFF_LEN`] bytes are inspected: a NUL byte or a high share
+//! of control characters marks a file as binary. Bytes above 0x7F count as
+//! text, since they are common in UTF-8 and single-byte encodings. Files with a
+//! byte order mark are always text, so UTF-16 is not mistaken for binary.
+
+use crate::core::encoding;
+use std::io::Read;
+use std::path::Path;
+
+/// Number of leading bytes inspected when sniffing
+pub const SNIFF_LEN: usize = 8 * 1024;
+
+/// Share of control characters above which a file is considered binary
+const MAX_CONTROL_RATIO: f64 = 0.3;
+
+/// Returns whether the leading bytes of a file look binary
+#[must_use]
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sample.is_empty() || encoding::detect_bom(sample).is_some() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+
+    let control = sample
+        .iter()
+        .filter(|&&byte| {
+            (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x08 | 0x0c | 0x1b))
+                || byte == 0x7f
+        })
+        .count();
+
+    #[allow(clippy::cast_precision_loss)] // sample is at most SNIFF_LEN bytes
+    let ratio = control as f64 / sample.len() as f64;
+    ratio > MAX_CONTROL_RATIO
+}
+
+/// Reads the first [`SNIFF_LEN`] bytes of `path` and reports whether they look binary
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read
+pub fn sniff_file(path: &Path) -> std::io::Result<bool> {
+    let mut sample = Vec::with_capacity(SNIFF_LEN);
+    std::fs::File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut sample)?;
+    Ok(is_binary(&sample))
+}
+
+/// Guesses the MIME type of a binary file from its magic number or extension
+#[must_use]
+pub fn guess_mime(path: &Path, bytes: &[u8]) -> &'static str {
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"MZ", "application/vnd.microsoft.portable-executable"),
+        (b"\0asm", "application/wasm"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"\xca\xfe\xba\xbe", "application/java-vm"),
+    ];
+
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return mime;
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "zip" | "jar" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "so" | "o" => "application/x-elf",
+        "exe" | "dll" => "application/vnd.microsoft.portable-executable",
+        "wasm" => "application/wasm",
+        "db" | "sqlite" | "sqlite3" => "application/vnd.sqlite3",
+        "class" => "application/java-vm",
+        "ttf" => "font/ttf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary(b""));
+        assert!(!is_binary(b"fn main() {\n\tprintln!(\"hi\");\n}\r\n"));
+        assert!(!is_binary("héllo wörld".as_bytes()));
+        assert!(!is_binary(&[b'a', 0xe9, b'b']));
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(is_binary(&[0x01, 0x02, 0x03, b'a', 0x04]));
+        assert!(!is_binary(&[0xff, 0xfe, b'a', 0x00, b'b', 0x00]));
+
+        let mut late_nul = vec![b'a'; SNIFF_LEN];
+        late_nul.push(0);
+        assert!(!is_binary(&late_nul));
+    }
+
+    #[test]
+    fn test_guess_mime() {
+        let png = Path::new("logo.bin");
+        assert_eq!(guess_mime(png, b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(
+            guess_mime(Path::new("data.db"), b"SQLite format 3\0"),
+            "application/vnd.sqlite3"
+        );
+        assert_eq!(guess_mime(Path::new("lib.so"), b"??"), "application/x-elf");
+        assert_eq!(
+            guess_mime(Path::new("blob"), b"\x01\x02"),
+            "application/octet-stream"
+        );
+    }
+}
@@ -0,0 +1,510 @@
+//! Local git metadata for the selected root
+//!
+//! The repository containing the root is read through libgit2, so no `git`
+//! executable is needed and nothing is fetched over the network. Paths are
+//! absolute and limited to files under the root.
+
+use crate::core::git_context::{CommitSummary, GitContext, GitContextConfig};
+use crate::core::types::CanonicalPath;
+use git2::{Delta, DiffFormat, DiffOptions, Repository, Status, StatusOptions};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Status badge of a changed file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GitStatus {
+    /// Changed in the index or working tree
+    Modified,
+    /// Added to the index
+    Added,
+    /// Not tracked by git
+    Untracked,
+}
+
+impl GitStatus {
+    /// Letter shown next to the file in the tree
+    #[must_use]
+    pub const fn badge(&self) -> &'static str {
+        match self {
+            Self::Modified => "M",
+            Self::Added => "A",
+            Self::Untracked => "?",
+        }
+    }
+
+    /// Human-readable label for tooltips
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Modified => "Modified",
+            Self::Added => "Added",
+            Self::Untracked => "Untracked",
+        }
+    }
+
+    const fn from_flags(status: Status) -> Self {
+        if status.contains(Status::INDEX_NEW) {
+            Self::Added
+        } else if status.contains(Status::WT_NEW) {
+            Self::Untracked
+        } else {
+            Self::Modified
+        }
+    }
+}
+
+/// Which changed files a git selection picks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSelection {
+    /// Files with unstaged changes in the working tree
+    Modified,
+    /// Files with staged changes
+    Staged,
+    /// Files git does not track
+    Untracked,
+    /// Files changed on HEAD since it branched off the given ref
+    BranchDiff(String),
+}
+
+impl GitSelection {
+    /// Description used in messages, e.g. "staged"
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Modified => "modified".to_string(),
+            Self::Staged => "staged".to_string(),
+            Self::Untracked => "untracked".to_string(),
+            Self::BranchDiff(base) => format!("changed since {base}"),
+        }
+    }
+}
+
+/// Working tree and index changes under a root
+///
+/// Deleted files have no entry in the tree to badge, so they only mark the
+/// directories above them.
+#[derive(Debug, Clone, Default)]
+pub struct GitChanges {
+    /// Status flags of every changed file on disk, keyed by absolute path
+    files: HashMap<PathBuf, Status>,
+    /// Directories that contain a changed file
+    dirs: HashSet<PathBuf>,
+}
+
+impl GitChanges {
+    /// Reads the status of the repository containing `root`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` is not inside a git work tree or the status
+    /// cannot be read
+    pub fn read(root: &CanonicalPath) -> Result<Self, String> {
+        let (repo, workdir) = open(root)?;
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .exclude_submodules(true);
+        let statuses = repo
+            .statuses(Some(&mut options))
+            .map_err(|e| format!("Failed to read git status: {}", e.message()))?;
+
+        let mut changes = Self::default();
+        for entry in statuses.iter() {
+            let Some(relative) = entry.path() else {
+                continue;
+            };
+            let path = workdir.join(relative);
+            if !path.starts_with(root.as_path()) {
+                continue;
+            }
+            for dir in path.ancestors().skip(1) {
+                if !dir.starts_with(root.as_path()) || !changes.dirs.insert(dir.to_path_buf()) {
+                    break;
+                }
+            }
+            let status = entry.status();
+            let deleted = status.intersects(Status::INDEX_DELETED | Status::WT_DELETED)
+                && !status.contains(Status::WT_NEW);
+            if !deleted {
+                changes.files.insert(path, status);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Returns the badge status of `path`, if the file changed
+    #[must_use]
+    pub fn status(&self, path: &Path) -> Option<GitStatus> {
+        self.files.get(path).copied().map(GitStatus::from_flags)
+    }
+
+    /// Returns whether `dir` contains a changed file
+    #[must_use]
+    pub fn contains_changes(&self, dir: &Path) -> bool {
+        self.dirs.contains(dir)
+    }
+
+    /// Returns the existing files picked by `selection`, sorted
+    ///
+    /// A branch diff is not part of the status; use [`select_files`] for it.
+    #[must_use]
+    pub fn select(&self, selection: &GitSelection) -> Vec<PathBuf> {
+        let flags = match selection {
+            GitSelection::Modified => {
+                Status::WT_MODIFIED | Status::WT_TYPECHANGE | Status::WT_RENAMED
+            }
+            GitSelection::Staged => {
+                Status::INDEX_NEW
+                    | Status::INDEX_MODIFIED
+                    | Status::INDEX_RENAMED
+                    | Status::INDEX_TYPECHANGE
+            }
+            GitSelection::Untracked => Status::WT_NEW,
+            GitSelection::BranchDiff(_) => return Vec::new(),
+        };
+        let mut files: Vec<PathBuf> = self
+            .files
+            .iter()
+            .filter(|(path, status)| status.intersects(flags) && path.is_file())
+            .map(|(path, _)| path.clone())
+            .collect();
+        files.sort();
+        files
+    }
+}
+
+/// Returns the existing files under `root` picked by `selection`, sorted
+///
+/// A branch diff compares HEAD with its merge base with the given ref, like
+/// `git diff <ref>...HEAD`, so commits made on the base since do not count.
+///
+/// # Errors
+///
+/// Returns an error if `root` is not inside a git work tree, the base ref
+/// does not resolve to a commit, or git metadata cannot be read
+pub fn select_files(
+    root: &CanonicalPath,
+    selection: &GitSelection,
+) -> Result<Vec<PathBuf>, String> {
+    let GitSelection::BranchDiff(base) = selection else {
+        return Ok(GitChanges::read(root)?.select(selection));
+    };
+
+    let (repo, workdir) = open(root)?;
+    let git_error = |e: git2::Error| format!("Failed to read git history: {}", e.message());
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(git_error)?;
+    let base_commit = repo
+        .revparse_single(base)
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|e| format!("Unknown base ref {base}: {}", e.message()))?;
+    let merge_base = branch_point(&repo, base_commit, &head);
+
+    let diff = repo
+        .diff_tree_to_tree(
+            Some(&merge_base.tree().map_err(git_error)?),
+            Some(&head.tree().map_err(git_error)?),
+            None,
+        )
+        .map_err(git_error)?;
+    let mut files: Vec<PathBuf> = diff
+        .deltas()
+        .filter(|delta| delta.status() != Delta::Deleted)
+        .filter_map(|delta| delta.new_file().path())
+        .map(|relative| workdir.join(relative))
+        .filter(|path| path.starts_with(root.as_path()) && path.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Reads the branch, recent commits and the diff of `files` for the git section
+///
+/// With a diff base the diff is `base...HEAD`, from the commit HEAD branched
+/// off the base at, otherwise the working tree and index against HEAD,
+/// including untracked files.
+///
+/// # Errors
+///
+/// Returns an error if `root` is not inside a git work tree, the diff base
+/// does not resolve to a commit, or git metadata cannot be read
+pub fn read_context(
+    root: &CanonicalPath,
+    files: &[CanonicalPath],
+    config: &GitContextConfig,
+) -> Result<GitContext, String> {
+    let (repo, workdir) = open(root)?;
+    let git_error = |e: git2::Error| format!("Failed to read git history: {}", e.message());
+    let summary = |commit: &git2::Commit<'_>| CommitSummary {
+        hash: commit.id().to_string(),
+        subject: commit.summary().unwrap_or_default().to_string(),
+    };
+
+    // An unborn branch has a name but no commit yet
+    let head_ref = repo.head().ok();
+    let head = head_ref
+        .as_ref()
+        .and_then(|head| head.peel_to_commit().ok());
+    let branch = if repo.head_detached().unwrap_or(false) {
+        None
+    } else {
+        head_ref
+            .as_ref()
+            .and_then(|head| head.shorthand().map(str::to_string))
+            .or_else(|| {
+                repo.find_reference("HEAD")
+                    .ok()
+                    .and_then(|head| head.symbolic_target().map(str::to_string))
+                    .map(|target| target.trim_start_matches("refs/heads/").to_string())
+            })
+    };
+
+    let mut recent_commits = Vec::new();
+    if let Some(head) = &head {
+        let mut walk = repo.revwalk().map_err(git_error)?;
+        walk.push(head.id()).map_err(git_error)?;
+        for oid in walk.take(config.commit_count) {
+            let commit = repo
+                .find_commit(oid.map_err(git_error)?)
+                .map_err(git_error)?;
+            recent_commits.push(summary(&commit));
+        }
+    }
+
+    let mut options = DiffOptions::new();
+    options.disable_pathspec_match(true);
+    for file in files {
+        if let Ok(relative) = file.as_path().strip_prefix(&workdir) {
+            options.pathspec(relative);
+        }
+    }
+    let head_tree = head
+        .as_ref()
+        .map(git2::Commit::tree)
+        .transpose()
+        .map_err(git_error)?;
+    let diff_base = Some(config.diff_base.trim()).filter(|base| !base.is_empty());
+    let diff = if let Some(base) = diff_base {
+        let base_commit = repo
+            .revparse_single(base)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("Unknown diff base {base}: {}", e.message()))?;
+        let base_commit = match &head {
+            Some(head) => branch_point(&repo, base_commit, head),
+            None => base_commit,
+        };
+        let base_tree = base_commit.tree().map_err(git_error)?;
+        repo.diff_tree_to_tree(Some(&base_tree), head_tree.as_ref(), Some(&mut options))
+    } else {
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut options))
+    }
+    .map_err(git_error)?;
+
+    let mut patch = String::new();
+    // Files with no selected paths would otherwise diff the whole repository
+    if !files.is_empty() {
+        diff.print(DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), '+' | '-' | ' ') {
+                patch.push(line.origin());
+            }
+            patch.push_str(&String::from_utf8_lossy(line.content()));
+            true
+        })
+        .map_err(git_error)?;
+    }
+
+    Ok(GitContext {
+        branch,
+        head: head.as_ref().map(summary),
+        recent_commits,
+        diff_base: diff_base.map(str::to_string),
+        diff: patch,
+    })
+}
+
+/// Returns the commit `head` branched off `base` at, like `git merge-base`
+///
+/// Falls back to `base` itself when the two histories share no commit.
+fn branch_point<'r>(
+    repo: &'r Repository,
+    base: git2::Commit<'r>,
+    head: &git2::Commit<'_>,
+) -> git2::Commit<'r> {
+    repo.merge_base(base.id(), head.id())
+        .and_then(|oid| repo.find_commit(oid))
+        .unwrap_or(base)
+}
+
+/// Opens the repository containing `root` and returns it with its canonical work tree
+fn open(root: &CanonicalPath) -> Result<(Repository, PathBuf), String> {
+    let repo = Repository::discover(root.as_path()).map_err(|_| {
+        format!(
+            "{} is not inside a git repository",
+            root.as_path().display()
+        )
+    })?;
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| "The git repository has no work tree".to_string())?
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve the git work tree: {e}"))?;
+    Ok((repo, workdir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<git2::Commit<'_>> = repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_commit().ok())
+            .into_iter()
+            .collect();
+        let parents: Vec<&git2::Commit<'_>> = parents.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_select_changed_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::write(root.join("kept.rs"), "fn kept() {}\n").unwrap();
+        fs::write(root.join("edited.rs"), "fn edited() {}\n").unwrap();
+        fs::create_dir(root.join("old")).unwrap();
+        fs::write(root.join("old/gone.rs"), "fn gone() {}\n").unwrap();
+        commit_all(&repo, "Initial");
+        let base = repo.head().unwrap().peel_to_commit().unwrap().id();
+        repo.branch("base", &repo.find_commit(base).unwrap(), false)
+            .unwrap();
+
+        fs::write(root.join("branch.rs"), "fn branch() {}\n").unwrap();
+        commit_all(&repo, "On branch");
+        fs::write(root.join("edited.rs"), "fn edited() { todo!() }\n").unwrap();
+        fs::write(root.join("staged.rs"), "fn staged() {}\n").unwrap();
+        repo.index()
+            .and_then(|mut index| {
+                index.add_path(Path::new("staged.rs"))?;
+                index.write()
+            })
+            .unwrap();
+        fs::write(root.join("new.rs"), "fn new() {}\n").unwrap();
+        fs::remove_file(root.join("old/gone.rs")).unwrap();
+
+        let root = CanonicalPath::new(root).unwrap();
+        let names = |selection: GitSelection| -> Vec<String> {
+            select_files(&root, &selection)
+                .unwrap()
+                .iter()
+                .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+                .collect()
+        };
+        assert_eq!(names(GitSelection::Modified), vec!["edited.rs"]);
+        assert_eq!(names(GitSelection::Staged), vec!["staged.rs"]);
+        assert_eq!(names(GitSelection::Untracked), vec!["new.rs"]);
+        assert_eq!(
+            names(GitSelection::BranchDiff("base".to_string())),
+            vec!["branch.rs"]
+        );
+        assert!(select_files(&root, &GitSelection::BranchDiff("nope".to_string())).is_err());
+
+        let changes = GitChanges::read(&root).unwrap();
+        let status = |name: &str| changes.status(&root.as_path().join(name));
+        assert_eq!(status("edited.rs"), Some(GitStatus::Modified));
+        assert_eq!(status("staged.rs"), Some(GitStatus::Added));
+        assert_eq!(status("new.rs"), Some(GitStatus::Untracked));
+        assert_eq!(status("kept.rs"), None);
+        // A deleted file only marks its directory
+        assert_eq!(status("old/gone.rs"), None);
+        assert!(changes.contains_changes(&root.as_path().join("old")));
+    }
+
+    #[test]
+    fn test_read_context_limits_diff_to_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let repo = Repository::init(root).unwrap();
+        fs::write(root.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn b() {}\n").unwrap();
+        commit_all(&repo, "Add a and b");
+        fs::write(root.join("a.rs"), "fn a() {}\nfn c() {}\n").unwrap();
+        commit_all(&repo, "Add c\n\nLonger description");
+        fs::write(root.join("a.rs"), "fn a() { todo!() }\nfn c() {}\n").unwrap();
+        fs::write(root.join("b.rs"), "fn b() { todo!() }\n").unwrap();
+
+        let root = CanonicalPath::new(root).unwrap();
+        let a = CanonicalPath::new(root.as_path().join("a.rs")).unwrap();
+        let mut config = GitContextConfig {
+            enabled: true,
+            commit_count: 1,
+            ..GitContextConfig::default()
+        };
+
+        let context = read_context(&root, std::slice::from_ref(&a), &config).unwrap();
+        assert!(context.branch.is_some());
+        assert_eq!(context.head.as_ref().unwrap().subject, "Add c");
+        assert_eq!(context.recent_commits.len(), 1);
+        assert!(context.diff.contains("-fn a() {}\n+fn a() { todo!() }\n"));
+        assert!(!context.diff.contains("b.rs"));
+
+        config.diff_base = "HEAD~1".to_string();
+        let context = read_context(&root, std::slice::from_ref(&a), &config).unwrap();
+        assert_eq!(context.diff_range(), "HEAD~1...HEAD");
+        assert!(context.diff.contains("+fn c() {}\n"));
+        assert!(!context.diff.contains("todo!"));
+
+        // Commits made on the base after branching off are not part of the diff
+        let fork = repo
+            .head()
+            .unwrap()
+            .peel_to_commit()
+            .unwrap()
+            .parent(0)
+            .unwrap();
+        let blob = repo.blob(b"fn other() {}\n").unwrap();
+        let mut builder = repo.treebuilder(Some(&fork.tree().unwrap())).unwrap();
+        builder.insert("a.rs", blob, 0o100_644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(
+            Some("refs/heads/other"),
+            &signature,
+            &signature,
+            "Other",
+            &tree,
+            &[&fork],
+        )
+        .unwrap();
+        config.diff_base = "other".to_string();
+        let context = read_context(&root, &[a], &config).unwrap();
+        assert!(context.diff.contains("+fn c() {}\n"));
+        assert!(!context.diff.contains("fn other"));
+    }
+}
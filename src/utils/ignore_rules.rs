@@ -0,0 +1,202 @@
+//! Ignore rules shared by the tree, the generator and file collection
+//!
+//! Combines the user's ignore patterns, which take precedence, with,
+//! optionally, the ignore files in
+//! the project: `.gitignore`, `.ignore` and `.fspromptignore` in every
+//! directory, and `.git/info/exclude` of the enclosing repository. Ignore
+//! files in directories above the root count too, up to the repository root.
+//! Every caller walks top-down and skips ignored directories, so only the
+//! entry itself is checked, never its parents.
+
+use crate::core::ignore::IgnorePatterns;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Ignore file names read in every directory, lowest precedence first
+pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", ".fspromptignore"];
+
+/// Decides which entries under a root are hidden
+#[derive(Debug)]
+pub struct IgnoreRules {
+    /// Directory the rules apply under
+    root: PathBuf,
+    /// User patterns
+    patterns: IgnorePatterns,
+    /// Ignore files, if honoured
+    files: Option<IgnoreFiles>,
+}
+
+/// Matchers built from the ignore files in the project
+#[derive(Debug)]
+struct IgnoreFiles {
+    /// Highest directory whose ignore files apply: the repository root, or the root
+    top: PathBuf,
+    /// `.git/info/exclude` of the enclosing repository
+    exclude: Option<Gitignore>,
+    /// Matcher per directory, built on first use; `None` without ignore files
+    dirs: RwLock<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl IgnoreRules {
+    /// Creates the rules for `root`
+    ///
+    /// Invalid patterns are skipped. With `respect_ignore_files`, the ignore
+    /// files of the project apply as well.
+    #[must_use]
+    pub fn new(root: &Path, patterns: &[String], respect_ignore_files: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            patterns: IgnorePatterns::new(root, patterns),
+            files: respect_ignore_files.then(|| IgnoreFiles::new(root)),
+        }
+    }
+
+    /// Rules that hide nothing
+    #[must_use]
+    pub fn none() -> Self {
+        Self {
+            root: PathBuf::new(),
+            patterns: IgnorePatterns::empty(),
+            files: None,
+        }
+    }
+
+    /// User patterns that were skipped because they failed to compile
+    #[must_use]
+    pub fn invalid_patterns(&self) -> &[String] {
+        self.patterns.invalid()
+    }
+
+    /// Returns whether ignore files are honoured
+    #[must_use]
+    pub const fn respects_ignore_files(&self) -> bool {
+        self.files.is_some()
+    }
+
+    /// Returns whether the entry at `path` is hidden
+    ///
+    /// `path` must be below the root, joined from the root rather than
+    /// canonicalized, so symlinks are judged by where they sit in the tree.
+    /// A user pattern that matches decides, so `!name` can re-include a file
+    /// hidden by an ignore file.
+    #[must_use]
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if let Some(ignored) = self.patterns.decide(path, is_dir) {
+            return ignored;
+        }
+        self.files
+            .as_ref()
+            .is_some_and(|files| path.starts_with(&self.root) && files.is_ignored(path, is_dir))
+    }
+}
+
+impl IgnoreFiles {
+    fn new(root: &Path) -> Self {
+        let repository = root
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .map(Path::to_path_buf);
+        // Exclude patterns are relative to the repository root, not `.git/info`
+        let exclude = repository.as_ref().and_then(|repository| {
+            let path = repository.join(".git").join("info").join("exclude");
+            if !path.is_file() {
+                return None;
+            }
+            let mut builder = GitignoreBuilder::new(repository);
+            let _ = builder.add(path);
+            builder.build().ok()
+        });
+        Self {
+            top: repository.unwrap_or_else(|| root.to_path_buf()),
+            exclude,
+            dirs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+        // The deepest ignore file with an opinion decides
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(&self.top) {
+                break;
+            }
+            if let Some(matcher) = self.matcher(dir) {
+                match matcher.matched(path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+        }
+        self.exclude
+            .as_ref()
+            .is_some_and(|exclude| exclude.matched(path, is_dir).is_ignore())
+    }
+
+    /// Returns the matcher for the ignore files in `dir`, building it on first use
+    fn matcher(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        if let Some(matcher) = self.dirs.read().ok()?.get(dir) {
+            return matcher.clone();
+        }
+        let mut builder = GitignoreBuilder::new(dir);
+        let mut found = false;
+        for name in IGNORE_FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                // Unreadable files and invalid lines are skipped
+                let _ = builder.add(path);
+                found = true;
+            }
+        }
+        let matcher = found.then(|| builder.build().ok()).flatten().map(Arc::new);
+        if let Ok(mut dirs) = self.dirs.write() {
+            dirs.insert(dir.to_path_buf(), matcher.clone());
+        }
+        matcher
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join(".git/info")).unwrap();
+        fs::create_dir_all(root.join("src/gen")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join(".git/info/exclude"), "scratch.rs\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "!keep.log\n").unwrap();
+        fs::write(root.join("src/.fspromptignore"), "gen/\n").unwrap();
+
+        let rules = IgnoreRules::new(root, &["*.tmp".to_string(), "!app.log".to_string()], true);
+        assert!(rules.is_ignored(&root.join("target"), true));
+        assert!(!rules.is_ignored(&root.join("target"), false));
+        assert!(!rules.is_ignored(&root.join("app.log"), false));
+        assert!(rules.is_ignored(&root.join("error.log"), false));
+        assert!(!rules.is_ignored(&root.join("src/keep.log"), false));
+        assert!(rules.is_ignored(&root.join("src/other.log"), false));
+        assert!(rules.is_ignored(&root.join("src/gen"), true));
+        assert!(rules.is_ignored(&root.join("src/scratch.rs"), false));
+        assert!(rules.is_ignored(&root.join(".git"), true));
+        assert!(rules.is_ignored(&root.join("src/a.tmp"), false));
+        assert!(!rules.is_ignored(&root.join("src/main.rs"), false));
+
+        // A subdirectory opened as the root still honours the parent files
+        let nested = IgnoreRules::new(&root.join("src"), &[], true);
+        assert!(nested.is_ignored(&root.join("src/other.log"), false));
+
+        let patterns_only = IgnoreRules::new(root, &["*.tmp".to_string()], false);
+        assert!(!patterns_only.is_ignored(&root.join("error.log"), false));
+        assert!(patterns_only.is_ignored(&root.join("a.tmp"), false));
+    }
+}
@@ -1,4 +1,8 @@
 //! Utility modules for performance-critical operations
 
+pub mod content;
+pub mod git;
+pub mod ignore_rules;
 pub mod parallel_fs;
 pub mod perf;
+pub mod rust_deps;
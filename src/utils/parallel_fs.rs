@@ -1,7 +1,11 @@
 //! Parallel filesystem operations for improved performance
 
+use crate::core::encoding::{self, EncodingConfig};
+use crate::core::query::glob_matches;
 use crate::core::types::CanonicalPath;
-use ignore::{overrides::OverrideBuilder, WalkBuilder, WalkState};
+use crate::utils::ignore_rules::IgnoreRules;
+use glob::Pattern;
+use ignore::{WalkBuilder, WalkState};
 use rayon::prelude::*;
 use std::collections::HashMap;
 use std::path::Path;
@@ -22,6 +26,8 @@ pub struct DirectoryEntry {
 
 /// Performs a parallel directory scan up to a specified depth
 ///
+/// Entries matching `ignore_patterns` are skipped; ignore files are not read.
+///
 /// # Errors
 ///
 /// Returns an empty vector if the root path cannot be canonicalized
@@ -30,41 +36,45 @@ pub fn scan_directory_parallel(
     max_depth: Option<usize>,
     ignore_patterns: &[String],
 ) -> Vec<DirectoryEntry> {
-    // Create canonical root for path validation
     let Ok(canonical_root) = CanonicalPath::new(root) else {
         return Vec::new(); // Return empty if root is invalid
     };
+    let rules = IgnoreRules::new(canonical_root.as_path(), ignore_patterns, false);
+    scan_directory_with_rules(&canonical_root, max_depth, Arc::new(rules))
+}
 
+/// Performs a parallel directory scan, skipping entries hidden by `rules`
+///
+/// `rules` must be built for `root`. Ignored directories are not descended into.
+pub fn scan_directory_with_rules(
+    root: &CanonicalPath,
+    max_depth: Option<usize>,
+    rules: Arc<IgnoreRules>,
+) -> Vec<DirectoryEntry> {
+    let canonical_root = root.clone();
     let entries = Arc::new(Mutex::new(Vec::new()));
     let entries_clone = Arc::clone(&entries);
 
-    let mut builder = WalkBuilder::new(root);
+    let mut builder = WalkBuilder::new(root.as_path());
 
-    // Configure the walker
+    // Configure the walker; ignore files are handled by the rules
     builder
-        .standard_filters(false) // Don't use .gitignore by default
+        .standard_filters(false)
         .hidden(false) // Show hidden files
-        .parents(false) // Don't look for .gitignore in parent dirs
         .follow_links(false) // Don't follow symlinks
-        .threads(num_cpus::get().min(8)); // Use up to 8 threads
+        .threads(num_cpus::get().min(8)) // Use up to 8 threads
+        .filter_entry(move |entry| {
+            entry.depth() == 0
+                || !rules.is_ignored(
+                    entry.path(),
+                    entry.file_type().is_some_and(|ft| ft.is_dir()),
+                )
+        });
 
     if let Some(depth) = max_depth {
         builder.max_depth(Some(depth));
     }
 
-    // Add ignore patterns
-    if !ignore_patterns.is_empty() {
-        let mut override_builder = OverrideBuilder::new(root);
-        for pattern in ignore_patterns {
-            if let Err(_e) = override_builder.add(&format!("!{pattern}")) {
-                // Silently ignore invalid patterns to avoid debug output
-            }
-        }
-        if let Ok(overrides) = override_builder.build() {
-            builder.overrides(overrides);
-        }
-    }
-
     let walker = builder.build_parallel();
 
     walker.run(|| {
@@ -129,19 +139,57 @@ pub fn build_tree_from_entries(
     tree
 }
 
+/// Collects the files under `root` that match the include globs and none of the exclude globs
+///
+/// With `respect_ignore_files`, files hidden by ignore files are left out too.
+/// The result is sorted so that exports are deterministic.
+pub fn collect_files(
+    root: &CanonicalPath,
+    include: &[String],
+    exclude: &[String],
+    respect_ignore_files: bool,
+) -> Vec<CanonicalPath> {
+    let include_patterns: Vec<Pattern> = include
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .collect();
+
+    let rules = IgnoreRules::new(root.as_path(), exclude, respect_ignore_files);
+    let mut files: Vec<CanonicalPath> = scan_directory_with_rules(root, None, Arc::new(rules))
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .filter(|entry| {
+            include_patterns.is_empty()
+                || entry
+                    .path
+                    .as_path()
+                    .strip_prefix(root.as_path())
+                    .is_ok_and(|relative| {
+                        include_patterns.iter().any(|p| glob_matches(p, relative))
+                    })
+        })
+        .map(|entry| entry.path)
+        .collect();
+
+    files.sort_by(|a, b| a.as_path().cmp(b.as_path()));
+    files
+}
+
 /// Read multiple files in parallel with path validation
-/// Validates all paths are within the root directory before reading
+/// Validates all paths are within the root directory before reading.
+/// Contents are decoded with `encoding`.
 ///
 /// # Errors
 ///
 /// Returns errors for:
 /// - Path traversal attempts (security error)
 /// - File read failures
-/// - UTF-8 decoding errors
+/// - Decoding errors (see [`encoding::decode`])
 pub fn read_files_parallel_secure(
     file_paths: &[CanonicalPath],
     root: &CanonicalPath,
     use_mmap_threshold: usize,
+    encoding: EncodingConfig,
 ) -> Vec<(CanonicalPath, Result<String, String>)> {
     file_paths
         .par_iter()
@@ -164,11 +212,12 @@ pub fn read_files_parallel_secure(
                 |metadata| {
                     if usize::try_from(metadata.len()).unwrap_or(usize::MAX) > use_mmap_threshold {
                         // Use memory-mapped reading for large files
-                        read_file_mmap(path.as_path())
+                        read_file_mmap(path.as_path(), encoding)
                     } else {
                         // Use standard reading for small files
-                        std::fs::read_to_string(path.as_path())
+                        std::fs::read(path.as_path())
                             .map_err(|e| format!("Failed to read file: {e}"))
+                            .and_then(|bytes| decode_text(bytes, encoding))
                     }
                 },
             );
@@ -180,14 +229,17 @@ pub fn read_files_parallel_secure(
 
 /// Parallel file reading with memory mapping for large files
 ///
+/// Contents are decoded with `encoding`.
+///
 /// # Errors
 ///
 /// Returns errors for:
 /// - File read failures
-/// - UTF-8 decoding errors
+/// - Decoding errors (see [`encoding::decode`])
 pub fn read_files_parallel(
     file_paths: &[CanonicalPath],
     use_mmap_threshold: usize,
+    encoding: EncodingConfig,
 ) -> Vec<(CanonicalPath, Result<String, String>)> {
     file_paths
         .par_iter()
@@ -202,11 +254,12 @@ pub fn read_files_parallel(
                 |metadata| {
                     if usize::try_from(metadata.len()).unwrap_or(usize::MAX) > use_mmap_threshold {
                         // Use memory-mapped reading for large files
-                        read_file_mmap(path.as_path())
+                        read_file_mmap(path.as_path(), encoding)
                     } else {
                         // Use standard reading for small files
-                        std::fs::read_to_string(path.as_path())
+                        std::fs::read(path.as_path())
                             .map_err(|e| format!("Failed to read file: {e}"))
+                            .and_then(|bytes| decode_text(bytes, encoding))
                     }
                 },
             );
@@ -223,8 +276,8 @@ pub fn read_files_parallel(
 /// Returns errors for:
 /// - File open failures
 /// - Memory mapping failures
-/// - UTF-8 decoding errors
-fn read_file_mmap(path: &Path) -> Result<String, String> {
+/// - Decoding errors (see [`encoding::decode`])
+fn read_file_mmap(path: &Path, encoding: EncodingConfig) -> Result<String, String> {
     use memmap2::Mmap;
     use std::fs::File;
 
@@ -233,76 +286,20 @@ fn read_file_mmap(path: &Path) -> Result<String, String> {
     let mmap =
         unsafe { Mmap::map(&file) }.map_err(|e| format!("Failed to create memory map: {e}"))?;
 
-    // Convert to string, handling UTF-8 errors
-    String::from_utf8(mmap.to_vec()).map_err(|e| format!("UTF-8 error: {e}"))
-}
-
-/// Pattern cache for improved glob matching performance
-pub struct PatternCache {
-    /// Compiled glob patterns
-    globs: Vec<glob::Pattern>,
-    /// Compiled regex patterns (as alternative)
-    regexes: Vec<regex::Regex>,
-}
-
-impl PatternCache {
-    /// Create a new pattern cache from glob patterns
-    pub fn new(patterns: &[String]) -> Self {
-        let globs = patterns
-            .iter()
-            .filter_map(|p| glob::Pattern::new(p).ok())
-            .collect();
-
-        let regexes = patterns
-            .iter()
-            .filter_map(|p| {
-                // Convert glob to regex
-                let regex_str = p
-                    .replace('.', "\\.")
-                    .replace('*', "[^/]*")
-                    .replace("**", ".*")
-                    .replace('{', "(")
-                    .replace('}', ")")
-                    .replace(',', "|");
-                regex::Regex::new(&format!("^{regex_str}$")).ok()
-            })
-            .collect();
-
-        Self { globs, regexes }
-    }
-
-    /// Check if a path matches any pattern
-    pub fn matches(&self, path: &str) -> bool {
-        // Try glob patterns first
-        for pattern in &self.globs {
-            if pattern.matches(path) {
-                return true;
-            }
-        }
-
-        // Fall back to regex if needed
-        for pattern in &self.regexes {
-            if pattern.is_match(path) {
-                return true;
-            }
-        }
-
-        false
-    }
+    decode_text(mmap.to_vec(), encoding)
 }
 
-impl std::fmt::Debug for PatternCache {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("PatternCache")
-            .field("globs", &format!("{} patterns", self.globs.len()))
-            .field("regexes", &format!("{} patterns", self.regexes.len()))
-            .finish()
-    }
+/// Decodes file contents with the given encoding settings
+fn decode_text(bytes: Vec<u8>, config: EncodingConfig) -> Result<String, String> {
+    encoding::decode(bytes, &config)
+        .map(|decoded| decoded.text)
+        .map_err(|e| format!("Decoding error: {e}"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::encoding::FallbackEncoding;
     use std::fs;
     use tempfile::TempDir;
 
@@ -328,19 +325,58 @@ mod tests {
     }
 
     #[test]
-    fn test_pattern_cache() {
-        let patterns = vec![
-            "*.rs".to_string(),
-            "target/**".to_string(),
-            "*.{txt,md}".to_string(),
-        ];
-
-        let cache = PatternCache::new(&patterns);
-
-        assert!(cache.matches("main.rs"));
-        assert!(cache.matches("target/debug/build"));
-        assert!(cache.matches("readme.txt"));
-        assert!(cache.matches("doc.md"));
-        assert!(!cache.matches("main.py"));
+    fn test_collect_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::create_dir(root.join("target")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/nested/lib.rs"), "pub fn lib() {}\n").unwrap();
+        fs::write(root.join("README.md"), "# readme").unwrap();
+        fs::write(root.join("target/out.rs"), "// build output").unwrap();
+
+        let canonical_root = CanonicalPath::new(root).unwrap();
+        let files = collect_files(
+            &canonical_root,
+            &["*.rs".to_string()],
+            &["target".to_string()],
+            true,
+        );
+        assert_eq!(files.len(), 2);
+        assert!(files[0].as_path().ends_with("src/main.rs"));
+
+        let anchored = collect_files(&canonical_root, &["src/*.rs".to_string()], &[], true);
+        assert_eq!(anchored.len(), 1);
+
+        fs::write(root.join(".gitignore"), "nested/\n").unwrap();
+        let ignored = collect_files(&canonical_root, &["*.rs".to_string()], &[], true);
+        assert_eq!(ignored.len(), 2);
+        assert!(ignored
+            .iter()
+            .all(|file| !file.as_path().ends_with("lib.rs")));
+        let unignored = collect_files(&canonical_root, &["*.rs".to_string()], &[], false);
+        assert_eq!(unignored.len(), 3);
+    }
+
+    #[test]
+    fn test_read_files_with_encoding_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("latin.txt");
+        fs::write(&path, b"caf\xe9\n").unwrap();
+        let files = vec![CanonicalPath::new(&path).unwrap()];
+
+        let strict = EncodingConfig {
+            fallback: FallbackEncoding::None,
+            lossy: false,
+            ..EncodingConfig::default()
+        };
+        // Both the plain and the memory-mapped read honour the settings
+        for threshold in [usize::MAX, 0] {
+            let results = read_files_parallel(&files, threshold, strict);
+            assert!(results[0].1.is_err());
+
+            let results = read_files_parallel(&files, threshold, EncodingConfig::default());
+            assert_eq!(results[0].1.as_deref(), Ok("café\n"));
+        }
     }
 }
@@ -0,0 +1,330 @@
+//! Related Rust files through module references
+//!
+//! Files are mapped to modules the way rustc lays out a crate: the crate
+//! root is `src/lib.rs` or `src/main.rs` of the nearest `Cargo.toml`, and
+//! `a::b` lives in `src/a/b.rs` or `src/a/b/mod.rs`. A `crate::` path
+//! resolves to the deepest module file it names, so `crate::core::types::X`
+//! gives `src/core/types.rs`, and a path starting with an item of the crate
+//! root, such as `crate::Error::new`, gives the root file. References that
+//! name no file are reported.
+
+use crate::core::rust_deps::{self, Reference};
+use crate::core::types::CanonicalPath;
+use crate::utils::ignore_rules::IgnoreRules;
+use crate::utils::parallel_fs::scan_directory_with_rules;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Which way references are followed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelatedDirection {
+    /// Files the selected files refer to
+    Dependencies,
+    /// Files referring to the selected files
+    Dependents,
+}
+
+/// A reference that names no file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unresolved {
+    /// File containing the reference
+    pub file: PathBuf,
+    /// The reference, such as `mod cache` or `crate::core::missing`
+    pub reference: String,
+}
+
+/// Files found from a selection
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelatedFiles {
+    /// Related files, sorted and without the starting files
+    pub files: Vec<PathBuf>,
+    /// References that could not be resolved, and starting files outside a crate
+    pub unresolved: Vec<Unresolved>,
+}
+
+/// Source layout of the crate containing a file
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct CrateLayout {
+    /// The `src` directory
+    src: PathBuf,
+    /// Names declared by each crate root file, `lib.rs` and `main.rs`
+    root_items: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl CrateLayout {
+    /// Finds the crate whose `src` directory contains `file`
+    fn containing(file: &Path) -> Option<Self> {
+        file.ancestors()
+            .skip(1)
+            .filter(|dir| dir.join("Cargo.toml").is_file())
+            .map(|dir| dir.join("src"))
+            .find(|src| file.starts_with(src))
+            .map(|src| {
+                let root_items = ["lib.rs", "main.rs"]
+                    .into_iter()
+                    .map(|name| src.join(name))
+                    .filter_map(|root| {
+                        let content = std::fs::read_to_string(&root).ok()?;
+                        Some((root, rust_deps::items(&content)))
+                    })
+                    .collect();
+                Self { src, root_items }
+            })
+    }
+
+    /// Returns the crate root file `file` belongs to
+    fn root_file(&self, file: &Path) -> PathBuf {
+        let main = self.src.join("main.rs");
+        let lib = self.src.join("lib.rs");
+        if file == main || !lib.is_file() {
+            main
+        } else {
+            lib
+        }
+    }
+
+    /// Returns whether `file` is a crate root or `mod.rs`, whose submodules sit next to it
+    fn owns_directory(&self, file: &Path) -> bool {
+        file.file_name().is_some_and(|name| name == "mod.rs")
+            || (file.parent() == Some(self.src.as_path())
+                && file
+                    .file_name()
+                    .is_some_and(|name| name == "lib.rs" || name == "main.rs"))
+    }
+
+    /// Returns the file of the module `segments`, if it exists
+    fn module_file(&self, segments: &[String]) -> Option<PathBuf> {
+        let dir = segments.iter().fold(self.src.clone(), |dir, s| dir.join(s));
+        [dir.with_extension("rs"), dir.join("mod.rs")]
+            .into_iter()
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Resolves a reference made in `file`
+    fn resolve(&self, file: &Path, reference: &Reference) -> Option<PathBuf> {
+        match reference {
+            Reference::Module(name) => {
+                let dir = if self.owns_directory(file) {
+                    file.parent()?.to_path_buf()
+                } else {
+                    file.with_extension("")
+                };
+                [
+                    dir.join(format!("{name}.rs")),
+                    dir.join(name).join("mod.rs"),
+                ]
+                .into_iter()
+                .find(|candidate| candidate.is_file())
+            }
+            Reference::CratePath(segments) => (1..=segments.len())
+                .rev()
+                .find_map(|len| self.module_file(&segments[..len]))
+                // Otherwise the path names an item of the crate root, such as
+                // `crate::Error::new`; a single segment always can
+                .or_else(|| {
+                    let root = self.root_file(file);
+                    (segments.len() == 1
+                        || self
+                            .root_items
+                            .get(&root)
+                            .is_some_and(|items| items.contains(&segments[0])))
+                    .then_some(root)
+                }),
+        }
+    }
+}
+
+/// Returns the files resolved from the references in `file`, with the references that failed
+fn dependencies_of(layout: &CrateLayout, file: &Path) -> (Vec<PathBuf>, Vec<Unresolved>) {
+    let Ok(content) = std::fs::read_to_string(file) else {
+        return (Vec::new(), Vec::new());
+    };
+    let mut files = Vec::new();
+    let mut unresolved = Vec::new();
+    for reference in rust_deps::references(&content) {
+        match layout.resolve(file, &reference) {
+            Some(found) if found != file => files.push(found),
+            Some(_) => {}
+            None => unresolved.push(Unresolved {
+                file: file.to_path_buf(),
+                reference: reference.to_string(),
+            }),
+        }
+    }
+    (files, unresolved)
+}
+
+/// Returns every `.rs` file under `dir` that `rules` leave visible
+fn rust_files(dir: &Path, rules: &Arc<IgnoreRules>) -> Vec<PathBuf> {
+    let Ok(dir) = CanonicalPath::new(dir) else {
+        return Vec::new();
+    };
+    scan_directory_with_rules(&dir, None, Arc::clone(rules))
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path.as_path().to_path_buf())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .collect()
+}
+
+/// Follows module references from `start` up to `depth` steps
+///
+/// Starting files that are not in a crate's `src` directory are reported as
+/// unresolved. Unresolved references are only reported for dependencies;
+/// looking for dependents, the other files' broken references are not the
+/// selection's concern. Dependents are looked for in the files `rules`
+/// leave visible, as in the tree.
+#[must_use]
+pub fn related_files(
+    start: &[PathBuf],
+    direction: RelatedDirection,
+    depth: usize,
+    rules: &Arc<IgnoreRules>,
+) -> RelatedFiles {
+    let mut result = RelatedFiles::default();
+    let mut layouts: BTreeMap<PathBuf, CrateLayout> = BTreeMap::new();
+    for file in start {
+        if file.extension().is_none_or(|ext| ext != "rs") {
+            continue;
+        }
+        match CrateLayout::containing(file) {
+            Some(layout) => {
+                layouts.insert(file.clone(), layout);
+            }
+            None => result.unresolved.push(Unresolved {
+                file: file.clone(),
+                reference: "not in the src directory of a Cargo crate".to_string(),
+            }),
+        }
+    }
+
+    // Dependents are found by inverting the references of every file of the crates involved
+    let mut dependents: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    if direction == RelatedDirection::Dependents {
+        let crates: BTreeSet<&CrateLayout> = layouts.values().collect();
+        for layout in crates {
+            for file in rust_files(&layout.src, rules) {
+                for dependency in dependencies_of(layout, &file).0 {
+                    dependents.entry(dependency).or_default().push(file.clone());
+                }
+            }
+        }
+    }
+
+    let mut visited: BTreeSet<PathBuf> = layouts.keys().cloned().collect();
+    let mut frontier: Vec<(PathBuf, CrateLayout)> = layouts.into_iter().collect();
+    for _ in 0..depth {
+        let mut next = Vec::new();
+        for (file, layout) in frontier {
+            let found = match direction {
+                RelatedDirection::Dependencies => {
+                    let (files, unresolved) = dependencies_of(&layout, &file);
+                    result.unresolved.extend(unresolved);
+                    files
+                }
+                RelatedDirection::Dependents => dependents.remove(&file).unwrap_or_default(),
+            };
+            for related in found {
+                if visited.insert(related.clone()) {
+                    result.files.push(related.clone());
+                    next.push((related, layout.clone()));
+                }
+            }
+        }
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
+    }
+
+    result.files.sort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_related_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let src = root.join("src");
+        fs::create_dir_all(src.join("ui")).unwrap();
+        fs::create_dir_all(src.join("core")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "pub mod core;\npub mod ui;\npub type Error = ();\n",
+        )
+        .unwrap();
+        fs::write(src.join("core/mod.rs"), "pub mod types;\n").unwrap();
+        fs::write(
+            src.join("core/types.rs"),
+            "use crate::Error;\npub struct Node;\n",
+        )
+        .unwrap();
+        fs::write(src.join("ui.rs"), "pub mod tree;\npub mod icons;\n").unwrap();
+        fs::write(
+            src.join("ui/tree.rs"),
+            "use crate::core::types::Node;\nuse crate::ui::icons::Icon;\nuse crate::missing::Thing;\n",
+        )
+        .unwrap();
+        fs::write(
+            src.join("ui/icons.rs"),
+            "pub struct Icon;\nfn fail() -> crate::Error { crate::Error::default() }\n",
+        )
+        .unwrap();
+        fs::write(src.join("ui/generated.rs"), "use crate::ui::icons::Icon;\n").unwrap();
+        fs::write(root.join("notes.rs"), "").unwrap();
+
+        let none = Arc::new(IgnoreRules::none());
+        let tree = src.join("ui/tree.rs");
+        let direct = related_files(
+            &[tree.clone(), root.join("notes.rs")],
+            RelatedDirection::Dependencies,
+            1,
+            &none,
+        );
+        assert_eq!(
+            direct.files,
+            vec![src.join("core/types.rs"), src.join("ui/icons.rs")]
+        );
+        let reported: Vec<&str> = direct
+            .unresolved
+            .iter()
+            .map(|u| u.reference.as_str())
+            .collect();
+        assert_eq!(
+            reported,
+            vec![
+                "not in the src directory of a Cargo crate",
+                "crate::missing::Thing"
+            ]
+        );
+
+        // A path through an item of the crate root resolves to the root file
+        let icons = src.join("ui/icons.rs");
+        let rooted = related_files(&[icons.clone()], RelatedDirection::Dependencies, 1, &none);
+        assert_eq!(rooted.files, vec![src.join("lib.rs")]);
+        assert!(rooted.unresolved.is_empty());
+
+        let transitive = related_files(&[tree.clone()], RelatedDirection::Dependencies, 2, &none);
+        assert_eq!(
+            transitive.files,
+            vec![
+                src.join("core/types.rs"),
+                src.join("lib.rs"),
+                src.join("ui/icons.rs")
+            ]
+        );
+
+        let rules = Arc::new(IgnoreRules::new(&root, &["generated.rs".to_string()], true));
+        let dependents = related_files(&[icons], RelatedDirection::Dependents, 1, &rules);
+        assert_eq!(dependents.files, vec![tree, src.join("ui.rs")]);
+        assert!(dependents.unresolved.is_empty());
+    }
+}
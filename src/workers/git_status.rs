@@ -0,0 +1,135 @@
+//! Background thread reading the git status shown in the tree
+//!
+//! Reading the status walks the whole work tree, which takes too long for
+//! the UI thread in a large repository. Requests arriving within
+//! [`DEBOUNCE`] of each other, such as a burst of watcher events, are
+//! answered once, for the most recent one.
+
+use crate::core::types::CanonicalPath;
+use crate::utils::git::GitChanges;
+use crossbeam::channel::{Receiver, Sender};
+use std::time::Duration;
+
+/// Time to wait for a newer request before reading the status
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Commands sent to the git status thread
+#[derive(Debug, Clone)]
+pub enum GitStatusCommand {
+    /// Read the status of the repository containing a root
+    Read {
+        /// Request number echoed in the result
+        id: u64,
+        /// Root whose changed files are badged
+        root: CanonicalPath,
+    },
+}
+
+/// Results sent from the git status thread
+#[derive(Debug, Clone)]
+pub enum GitStatusEvent {
+    /// Status of a root
+    Read {
+        /// Request number of the read
+        id: u64,
+        /// Changed files, or `None` outside a git work tree
+        changes: Option<GitChanges>,
+    },
+}
+
+/// Handle for communicating with the git status thread
+#[derive(Debug)]
+pub struct GitStatusHandle {
+    sender: Sender<GitStatusCommand>,
+    receiver: Receiver<GitStatusEvent>,
+}
+
+impl GitStatusHandle {
+    /// Creates a handle and spawns the git status thread
+    #[must_use]
+    pub fn new() -> Self {
+        let (cmd_tx, cmd_rx) = crossbeam::channel::unbounded();
+        let (event_tx, event_rx) = crossbeam::channel::unbounded();
+
+        std::thread::spawn(move || {
+            run_git_status_worker(&cmd_rx, &event_tx);
+        });
+
+        Self {
+            sender: cmd_tx,
+            receiver: event_rx,
+        }
+    }
+
+    /// Sends a command to the git status thread
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the git status thread has terminated
+    pub fn send_command(
+        &self,
+        command: GitStatusCommand,
+    ) -> Result<(), crossbeam::channel::SendError<GitStatusCommand>> {
+        self.sender.send(command)
+    }
+
+    /// Tries to receive a result from the git status thread
+    pub fn try_recv_event(&self) -> Option<GitStatusEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Default for GitStatusHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Main function of the git status thread
+pub fn run_git_status_worker(
+    cmd_rx: &Receiver<GitStatusCommand>,
+    event_tx: &Sender<GitStatusEvent>,
+) {
+    while let Ok(mut command) = cmd_rx.recv() {
+        // Wait for the burst of requests to settle, answering only the last
+        while let Ok(newer) = cmd_rx.recv_timeout(DEBOUNCE) {
+            command = newer;
+        }
+        let GitStatusCommand::Read { id, root } = command;
+        let changes = GitChanges::read(&root).ok();
+        if event_tx.send(GitStatusEvent::Read { id, changes }).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_burst_is_read_once() {
+        let temp_dir = TempDir::new().unwrap();
+        git2::Repository::init(temp_dir.path()).unwrap();
+        std::fs::write(temp_dir.path().join("new.rs"), "fn new() {}\n").unwrap();
+        let root = CanonicalPath::new(temp_dir.path()).unwrap();
+
+        let handle = GitStatusHandle::new();
+        for id in 1..=3 {
+            handle
+                .send_command(GitStatusCommand::Read {
+                    id,
+                    root: root.clone(),
+                })
+                .unwrap();
+        }
+        let GitStatusEvent::Read { id, changes } = handle.receiver.recv().unwrap();
+        assert_eq!(id, 3);
+        assert!(changes
+            .unwrap()
+            .status(&root.as_path().join("new.rs"))
+            .is_some());
+        assert!(handle.receiver.recv_timeout(DEBOUNCE * 2).is_err());
+    }
+}
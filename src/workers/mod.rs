@@ -1,8 +1,95 @@
+use crate::core::budget::{BudgetReport, TokenBudget};
+use crate::core::encoding::EncodingConfig;
+use crate::core::git_context::GitContextConfig;
+use crate::core::line_numbers::LineNumbers;
+use crate::core::secrets::{SecretConfig, SecretFinding};
+use crate::core::size_cap::FileSizeCap;
+use crate::core::split::SplitLimit;
+use crate::core::tokenizer::TokenizerModel;
+use crate::core::transform::{TransformConfig, TransformReport};
 use crate::core::types::{CanonicalPath, OutputFormat, PatternString, ProgressCount, TokenCount};
 use crossbeam::channel::{Receiver, Sender};
 
 /// Output generation worker
 pub mod generator;
+/// Background git status reads for the tree badges
+pub mod git_status;
+/// Background searches for bulk selection
+pub mod search;
+/// Background token counting for the selection estimate
+pub mod tokens;
+
+/// Options controlling how output is generated
+#[derive(Debug, Clone, Default)]
+pub struct GenerationOptions {
+    /// Output format
+    pub format: OutputFormat,
+    /// Whether to include directory tree
+    pub include_tree: bool,
+    /// Ignore patterns (comma-separated)
+    pub ignore_patterns: PatternString,
+    /// Whether `.gitignore` and the other ignore files hide entries in the tree
+    pub respect_ignore_files: bool,
+    /// Tokenizer used for token counts
+    pub tokenizer: TokenizerModel,
+    /// Token budget to pack the selection into, if enforced
+    pub token_budget: Option<TokenBudget>,
+    /// Files the budget packer must keep
+    pub pinned_files: Vec<CanonicalPath>,
+    /// Limit to split the output into parts at, if enabled
+    pub split: Option<SplitLimit>,
+    /// Include binary files as placeholder entries instead of skipping them
+    pub binary_placeholders: bool,
+    /// How file contents are decoded
+    pub encoding: EncodingConfig,
+    /// Secret redaction, if enabled
+    pub secrets: Option<SecretConfig>,
+    /// Cap on the size of a single file, if enforced
+    pub size_cap: Option<FileSizeCap>,
+    /// Content transforms run before building output
+    pub transforms: TransformConfig,
+    /// Files reduced to an outline of their declarations
+    pub outline_files: Vec<CanonicalPath>,
+    /// Line numbering, if enabled
+    pub line_numbers: Option<LineNumbers>,
+    /// Git section, if enabled
+    pub git_context: Option<GitContextConfig>,
+}
+
+/// Why a selected file was left out of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The file looks binary
+    Binary,
+    /// The file exceeds the per-file size cap
+    TooLarge,
+}
+
+impl SkipReason {
+    /// Status shown next to the file
+    #[must_use]
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Binary => "skipped: binary",
+            Self::TooLarge => "skipped: too large",
+        }
+    }
+}
+
+/// A selected file the generator deliberately left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedFile {
+    /// Root-relative path
+    pub path: String,
+    /// Why it was left out
+    pub reason: SkipReason,
+}
+
+impl std::fmt::Display for SkippedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.path, self.reason.label())
+    }
+}
 
 /// Commands sent to worker threads
 #[derive(Debug, Clone)]
@@ -13,12 +100,8 @@ pub enum WorkerCommand {
         root_path: CanonicalPath,
         /// List of selected files
         selected_files: Vec<CanonicalPath>,
-        /// Output format
-        format: OutputFormat,
-        /// Whether to include directory tree
-        include_tree: bool,
-        /// Ignore patterns (comma-separated)
-        ignore_patterns: PatternString,
+        /// Options controlling the generated output
+        options: Box<GenerationOptions>,
     },
     /// Cancel current operation
     Cancel,
@@ -36,13 +119,25 @@ pub enum WorkerEvent {
     },
     /// Output generation complete
     OutputReady {
-        /// Generated content
-        content: String,
+        /// Generated content, one document per part (a single one unless splitting)
+        parts: Vec<String>,
         /// Estimated token count
         token_count: TokenCount,
+        /// Files dropped or truncated to fit the token budget
+        budget_report: Option<BudgetReport>,
+        /// Files left out of the output, such as binaries
+        skipped: Vec<SkippedFile>,
+        /// Files whose undecodable bytes were replaced with U+FFFD
+        lossy_files: Vec<String>,
+        /// Token savings of the content transforms, if any ran
+        transform_report: Option<TransformReport>,
+        /// Secrets redacted from the output
+        secrets: Vec<SecretFinding>,
     },
     /// Error occurred
     Error(String),
+    /// Something was left out, but the output is still complete otherwise
+    Warning(String),
     /// Operation cancelled
     Cancelled,
 }
@@ -54,6 +149,8 @@ pub enum ProgressStage {
     ScanningFiles,
     /// Reading file contents
     ReadingFiles,
+    /// Reading branch, commits and diff for the git section
+    ReadingGitHistory,
     /// Building final output
     BuildingOutput,
 }
@@ -0,0 +1,497 @@
+//! Background searches that drive bulk selection
+//!
+//! Searches walk the whole root, including directories the tree has not
+//! loaded yet, with the same ignore rules as the tree. Only the most recent
+//! request of each kind is answered: older requests of the same kind queued
+//! behind it are dropped, and a content search stops as soon as a newer
+//! content search arrives, so typing a query never builds up a backlog of
+//! walks. Content searches skip binary files and files larger than
+//! [`MAX_SEARCH_FILE_SIZE`]. Following Rust module references for
+//! "Select related" runs here too, as looking for dependents reads every
+//! file of the crate.
+
+use crate::core::content_search::{ContentMatch, ContentQuery};
+use crate::core::encoding::{self, EncodingConfig};
+use crate::core::query::PathQuery;
+use crate::core::types::CanonicalPath;
+use crate::utils::content;
+use crate::utils::ignore_rules::IgnoreRules;
+use crate::utils::parallel_fs::{collect_files, scan_directory_with_rules};
+use crate::utils::rust_deps::{self, RelatedDirection, RelatedFiles};
+use crossbeam::channel::{Receiver, Sender};
+use rayon::prelude::*;
+use std::mem::discriminant;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Files larger than this many bytes are not searched
+pub const MAX_SEARCH_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Commands sent to the search thread
+#[derive(Debug, Clone)]
+pub enum SearchCommand {
+    /// Find the files whose root-relative path matches a query
+    MatchPaths {
+        /// Request number echoed in the result
+        id: u64,
+        /// Root directory to walk
+        root: CanonicalPath,
+        /// Ignore patterns of the tree
+        ignore_patterns: Vec<String>,
+        /// Whether ignore files hide entries, as in the tree
+        respect_ignore_files: bool,
+        /// Compiled query
+        query: PathQuery,
+    },
+    /// Find the files whose contents match a search
+    SearchContent {
+        /// Request number echoed in the result
+        id: u64,
+        /// Root directory to walk
+        root: CanonicalPath,
+        /// Ignore patterns of the tree
+        ignore_patterns: Vec<String>,
+        /// Whether ignore files hide entries, as in the tree
+        respect_ignore_files: bool,
+        /// Compiled search
+        query: ContentQuery,
+        /// How file contents are decoded
+        encoding: EncodingConfig,
+    },
+    /// Collect the files matching include globs, such as a project's default selection
+    CollectFiles {
+        /// Request number echoed in the result
+        id: u64,
+        /// Root directory to walk
+        root: CanonicalPath,
+        /// Globs a file must match one of; empty matches every file
+        include: Vec<String>,
+        /// Ignore patterns of the tree
+        exclude: Vec<String>,
+        /// Whether ignore files hide entries, as in the tree
+        respect_ignore_files: bool,
+    },
+    /// Follow the module references of Rust files
+    RelatedFiles {
+        /// Request number echoed in the result
+        id: u64,
+        /// Root directory whose ignore rules apply
+        root: CanonicalPath,
+        /// Ignore patterns of the tree
+        ignore_patterns: Vec<String>,
+        /// Whether ignore files hide entries, as in the tree
+        respect_ignore_files: bool,
+        /// Files to start from
+        start: Vec<PathBuf>,
+        /// Which way references are followed
+        direction: RelatedDirection,
+        /// Number of steps to follow
+        depth: usize,
+    },
+}
+
+impl SearchCommand {
+    /// Whether `other` is a request of the same kind, which supersedes this one
+    fn same_kind(&self, other: &Self) -> bool {
+        discriminant(self) == discriminant(other)
+    }
+}
+
+/// Results sent from the search thread
+#[derive(Debug, Clone)]
+pub enum SearchEvent {
+    /// Files matching a path query, sorted
+    PathMatches {
+        /// Request number of the query
+        id: u64,
+        /// Absolute paths of the matching files
+        files: Vec<PathBuf>,
+    },
+    /// Files whose contents match a search, sorted by path
+    ContentMatches {
+        /// Request number of the search
+        id: u64,
+        /// Matches per file
+        matches: Vec<ContentMatch>,
+    },
+    /// Files collected by include globs, sorted
+    CollectedFiles {
+        /// Request number of the collection
+        id: u64,
+        /// Absolute paths of the files
+        files: Vec<PathBuf>,
+    },
+    /// Rust files related through module references
+    RelatedFiles {
+        /// Request number of the lookup
+        id: u64,
+        /// Which way references were followed
+        direction: RelatedDirection,
+        /// Related files and the references that failed
+        related: RelatedFiles,
+    },
+}
+
+/// Handle for communicating with the search thread
+#[derive(Debug)]
+pub struct SearchHandle {
+    sender: Sender<SearchCommand>,
+    receiver: Receiver<SearchEvent>,
+}
+
+impl SearchHandle {
+    /// Creates a handle and spawns the search thread
+    #[must_use]
+    pub fn new() -> Self {
+        let (cmd_tx, cmd_rx) = crossbeam::channel::unbounded();
+        let (event_tx, event_rx) = crossbeam::channel::unbounded();
+
+        std::thread::spawn(move || {
+            run_search_worker(&cmd_rx, &event_tx);
+        });
+
+        Self {
+            sender: cmd_tx,
+            receiver: event_rx,
+        }
+    }
+
+    /// Sends a command to the search thread
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the search thread has terminated
+    pub fn send_command(
+        &self,
+        command: SearchCommand,
+    ) -> Result<(), crossbeam::channel::SendError<SearchCommand>> {
+        self.sender.send(command)
+    }
+
+    /// Tries to receive a result from the search thread
+    pub fn try_recv_event(&self) -> Option<SearchEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Default for SearchHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Queues `command`, replacing a pending request of the same kind
+fn enqueue(pending: &mut Vec<SearchCommand>, command: SearchCommand) {
+    match pending.iter_mut().find(|queued| queued.same_kind(&command)) {
+        Some(queued) => *queued = command,
+        None => pending.push(command),
+    }
+}
+
+/// Main function of the search thread
+pub fn run_search_worker(cmd_rx: &Receiver<SearchCommand>, event_tx: &Sender<SearchEvent>) {
+    let pending = Mutex::new(Vec::new());
+    // Moves newly arrived commands into the queue
+    let receive = || {
+        let mut pending = pending
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        for command in cmd_rx.try_iter() {
+            enqueue(&mut pending, command);
+        }
+    };
+
+    loop {
+        receive();
+        let next = {
+            let mut pending = pending
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            if pending.is_empty() {
+                None
+            } else {
+                Some(pending.remove(0))
+            }
+        };
+        let command = match next {
+            Some(command) => command,
+            None => match cmd_rx.recv() {
+                Ok(command) => command,
+                Err(_) => break,
+            },
+        };
+
+        let event = match command {
+            SearchCommand::MatchPaths {
+                id,
+                root,
+                ignore_patterns,
+                respect_ignore_files,
+                query,
+            } => Some(SearchEvent::PathMatches {
+                id,
+                files: match_paths(&root, &ignore_patterns, respect_ignore_files, &query),
+            }),
+            SearchCommand::SearchContent {
+                ref root,
+                ref ignore_patterns,
+                respect_ignore_files,
+                ref query,
+                encoding,
+                id,
+            } => search_contents(
+                root,
+                ignore_patterns,
+                respect_ignore_files,
+                query,
+                encoding,
+                &|| {
+                    receive();
+                    pending
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner)
+                        .iter()
+                        .any(|queued| queued.same_kind(&command))
+                },
+            )
+            .map(|matches| SearchEvent::ContentMatches { id, matches }),
+            SearchCommand::CollectFiles {
+                id,
+                root,
+                include,
+                exclude,
+                respect_ignore_files,
+            } => Some(SearchEvent::CollectedFiles {
+                id,
+                files: collect_files(&root, &include, &exclude, respect_ignore_files)
+                    .into_iter()
+                    .map(|file| file.as_path().to_path_buf())
+                    .collect(),
+            }),
+            SearchCommand::RelatedFiles {
+                id,
+                root,
+                ignore_patterns,
+                respect_ignore_files,
+                start,
+                direction,
+                depth,
+            } => {
+                let rules =
+                    IgnoreRules::new(root.as_path(), &ignore_patterns, respect_ignore_files);
+                Some(SearchEvent::RelatedFiles {
+                    id,
+                    direction,
+                    related: rust_deps::related_files(&start, direction, depth, &Arc::new(rules)),
+                })
+            }
+        };
+        if let Some(event) = event {
+            if event_tx.send(event).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Returns the sorted files under `root` whose root-relative path matches `query`
+#[must_use]
+pub fn match_paths(
+    root: &CanonicalPath,
+    ignore_patterns: &[String],
+    respect_ignore_files: bool,
+    query: &PathQuery,
+) -> Vec<PathBuf> {
+    let rules = IgnoreRules::new(root.as_path(), ignore_patterns, respect_ignore_files);
+    let mut files: Vec<PathBuf> = scan_directory_with_rules(root, None, Arc::new(rules))
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .filter(|entry| {
+            entry
+                .path
+                .as_path()
+                .strip_prefix(root.as_path())
+                .is_ok_and(|relative| query.matches(relative))
+        })
+        .map(|entry| entry.path.as_path().to_path_buf())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Searches the contents of the text files under `root`, returning the matches sorted by path
+///
+/// Returns `None` if `superseded` reports a newer request before the
+/// search finishes.
+pub fn search_contents(
+    root: &CanonicalPath,
+    ignore_patterns: &[String],
+    respect_ignore_files: bool,
+    query: &ContentQuery,
+    encoding: EncodingConfig,
+    superseded: &(dyn Fn() -> bool + Sync),
+) -> Option<Vec<ContentMatch>> {
+    let rules = IgnoreRules::new(root.as_path(), ignore_patterns, respect_ignore_files);
+    let files: Vec<PathBuf> = scan_directory_with_rules(root, None, Arc::new(rules))
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| entry.path.as_path().to_path_buf())
+        .collect();
+    // A preview is better than no result for a file with a few bad bytes
+    let encoding = EncodingConfig {
+        lossy: true,
+        ..encoding
+    };
+
+    let results: Option<Vec<Option<ContentMatch>>> = files
+        .par_iter()
+        .map(|path| (!superseded()).then(|| search_file(path, query, encoding)))
+        .collect();
+    let mut matches: Vec<ContentMatch> = results?.into_iter().flatten().collect();
+    matches.sort_by(|a, b| a.path.cmp(&b.path));
+    Some(matches)
+}
+
+/// Searches one file, skipping it if it is too large, binary or unreadable
+fn search_file(path: &Path, query: &ContentQuery, config: EncodingConfig) -> Option<ContentMatch> {
+    if std::fs::metadata(path).ok()?.len() > MAX_SEARCH_FILE_SIZE {
+        return None;
+    }
+    let bytes = std::fs::read(path).ok()?;
+    if content::is_binary(&bytes) {
+        return None;
+    }
+    let text = encoding::decode(bytes, &config).ok()?.text;
+    let (hits, preview) = query.search(&text)?;
+    Some(ContentMatch {
+        path: path.to_path_buf(),
+        hits,
+        preview,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_match_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("api/v1/tests")).unwrap();
+        fs::create_dir_all(root.join("api/generated")).unwrap();
+        fs::write(root.join("api/user.proto"), "").unwrap();
+        fs::write(root.join("api/v1/billing.proto"), "").unwrap();
+        fs::write(root.join("api/v1/tests/fixture.proto"), "").unwrap();
+        fs::write(root.join("api/generated/user.proto"), "").unwrap();
+        fs::write(root.join("api/.gitignore"), "generated/\n").unwrap();
+
+        let root = CanonicalPath::new(root).unwrap();
+        let query = PathQuery::parse("api/**/*.proto !**/tests/**").unwrap();
+        let files = match_paths(&root, &[], true, &query);
+        let relative: Vec<_> = files
+            .iter()
+            .map(|file| file.strip_prefix(root.as_path()).unwrap())
+            .collect();
+        assert_eq!(
+            relative,
+            [
+                PathBuf::from("api/user.proto"),
+                PathBuf::from("api/v1/billing.proto")
+            ]
+        );
+        assert_eq!(match_paths(&root, &[], false, &query).len(), 3);
+
+        let handle = SearchHandle::new();
+        handle
+            .send_command(SearchCommand::MatchPaths {
+                id: 7,
+                root,
+                ignore_patterns: vec!["v1".to_string()],
+                respect_ignore_files: true,
+                query,
+            })
+            .unwrap();
+        let Ok(SearchEvent::PathMatches { id, files }) = handle.receiver.recv() else {
+            panic!("expected path matches");
+        };
+        assert_eq!((id, files.len()), (7, 1));
+    }
+
+    #[test]
+    fn test_search_contents() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/auth.rs"), "fn login() {}\nfn logout() {}\n").unwrap();
+        fs::write(root.join("src/db.rs"), "fn connect() {}\n").unwrap();
+        fs::write(root.join("src/ignored.rs"), "fn login() {}\n").unwrap();
+        fs::write(root.join("blob.bin"), b"login\0\0\0").unwrap();
+        fs::write(root.join(".gitignore"), "ignored.rs\n").unwrap();
+
+        let root = CanonicalPath::new(root).unwrap();
+        let query = ContentQuery::new("log", false, true).unwrap();
+        let matches = search_contents(&root, &[], true, &query, EncodingConfig::default(), &|| {
+            false
+        })
+        .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(matches[0].path.ends_with("src/auth.rs"));
+        assert_eq!(matches[0].hits, 2);
+        assert_eq!(matches[0].preview.len(), 2);
+
+        let superseded =
+            search_contents(&root, &[], true, &query, EncodingConfig::default(), &|| {
+                true
+            });
+        assert!(superseded.is_none());
+    }
+
+    #[test]
+    fn test_requests_of_other_kinds_are_kept() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.rs"), "").unwrap();
+        fs::write(temp_dir.path().join("b.md"), "").unwrap();
+        let root = CanonicalPath::new(temp_dir.path()).unwrap();
+
+        let handle = SearchHandle::new();
+        handle
+            .send_command(SearchCommand::CollectFiles {
+                id: 1,
+                root: root.clone(),
+                include: vec!["*.rs".to_string()],
+                exclude: Vec::new(),
+                respect_ignore_files: true,
+            })
+            .unwrap();
+        for id in 2..=3 {
+            handle
+                .send_command(SearchCommand::MatchPaths {
+                    id,
+                    root: root.clone(),
+                    ignore_patterns: Vec::new(),
+                    respect_ignore_files: true,
+                    query: PathQuery::parse("*.md").unwrap(),
+                })
+                .unwrap();
+        }
+
+        // A newer path query supersedes older ones, but not the collection
+        let mut collected = None;
+        let mut last_query = None;
+        while collected.is_none() || last_query != Some(3) {
+            match handle.receiver.recv().unwrap() {
+                SearchEvent::CollectedFiles { id, files } => collected = Some((id, files)),
+                SearchEvent::PathMatches { id, .. } => last_query = Some(id),
+                SearchEvent::ContentMatches { .. } | SearchEvent::RelatedFiles { .. } => {
+                    unreachable!()
+                }
+            }
+        }
+        let (id, files) = collected.unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(files, vec![root.as_path().join("a.rs")]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Background thread counting the tokens of the selection
+//!
+//! Tokenizing a large selection with a BPE tokenizer takes long enough to
+//! freeze a frame, so the estimate in the footer is computed here. Like the
+//! search thread, only the most recent request is answered.
+
+use crate::core::encoding::EncodingConfig;
+use crate::core::tokenizer::{FileTokenCache, TokenizerModel};
+use crossbeam::channel::{Receiver, Sender};
+use std::path::PathBuf;
+
+/// Commands sent to the token thread
+#[derive(Debug, Clone)]
+pub enum TokenCommand {
+    /// Count the tokens of a selection
+    Count {
+        /// Request number echoed in the result
+        id: u64,
+        /// Files of the selection
+        files: Vec<PathBuf>,
+        /// Tokenizer to count with
+        model: TokenizerModel,
+        /// How file contents are decoded
+        encoding: EncodingConfig,
+        /// Cached counts of the files, see [`FileTokenCache::subset`]
+        cache: FileTokenCache,
+    },
+}
+
+/// Results sent from the token thread
+#[derive(Debug, Clone)]
+pub enum TokenEvent {
+    /// Token count of a selection
+    Counted {
+        /// Request number of the count
+        id: u64,
+        /// Total tokens of the files
+        total: usize,
+        /// Counts of the files, to merge into the app's cache
+        cache: FileTokenCache,
+    },
+}
+
+/// Handle for communicating with the token thread
+#[derive(Debug)]
+pub struct TokenHandle {
+    sender: Sender<TokenCommand>,
+    receiver: Receiver<TokenEvent>,
+}
+
+impl TokenHandle {
+    /// Creates a handle and spawns the token thread
+    #[must_use]
+    pub fn new() -> Self {
+        let (cmd_tx, cmd_rx) = crossbeam::channel::unbounded();
+        let (event_tx, event_rx) = crossbeam::channel::unbounded();
+
+        std::thread::spawn(move || {
+            run_token_worker(&cmd_rx, &event_tx);
+        });
+
+        Self {
+            sender: cmd_tx,
+            receiver: event_rx,
+        }
+    }
+
+    /// Sends a command to the token thread
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token thread has terminated
+    pub fn send_command(
+        &self,
+        command: TokenCommand,
+    ) -> Result<(), crossbeam::channel::SendError<TokenCommand>> {
+        self.sender.send(command)
+    }
+
+    /// Tries to receive a result from the token thread
+    pub fn try_recv_event(&self) -> Option<TokenEvent> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+impl Default for TokenHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Main function of the token thread
+pub fn run_token_worker(cmd_rx: &Receiver<TokenCommand>, event_tx: &Sender<TokenEvent>) {
+    while let Ok(mut command) = cmd_rx.recv() {
+        // Skip requests that a newer one has superseded
+        while let Ok(newer) = cmd_rx.try_recv() {
+            command = newer;
+        }
+        let TokenCommand::Count {
+            id,
+            files,
+            model,
+            encoding,
+            mut cache,
+        } = command;
+        let total = cache.count_files(files.iter().map(PathBuf::as_path), model, encoding);
+        if event_tx
+            .send(TokenEvent::Counted { id, total, cache })
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_count_in_background() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("a.txt");
+        std::fs::write(&path, "hello world").unwrap();
+
+        let handle = TokenHandle::new();
+        handle
+            .send_command(TokenCommand::Count {
+                id: 3,
+                files: vec![path.clone()],
+                model: TokenizerModel::Cl100k,
+                encoding: EncodingConfig::default(),
+                cache: FileTokenCache::new(),
+            })
+            .unwrap();
+        let TokenEvent::Counted { id, total, cache } = handle.receiver.recv().unwrap();
+        assert_eq!((id, total), (3, 2));
+
+        let mut merged = FileTokenCache::new();
+        merged.merge(cache);
+        let mut subset = merged.subset([path.as_path(), temp_dir.path()]);
+        assert_eq!(
+            subset.count_files(
+                [path.as_path()],
+                TokenizerModel::Cl100k,
+                EncodingConfig::default()
+            ),
+            2
+        );
+    }
+}